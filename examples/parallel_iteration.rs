@@ -6,17 +6,22 @@ use rayon::prelude::*;
 
 pub fn main() {
     // Create a new arena
-    let arena = &mut Tree::new();
+    let arena = &mut Tree::with_capacity(10_000_000);
 
     // Add some new nodes to the arena
     println!("Creating arena tree");
-    let mut last_node = arena.new_node(1);
+    let root = arena.new_node(1);
+    let mut last_node = root;
     for i in 1..10_000_000 {
         let node = arena.new_node(i);
-        node.append(last_node, arena);
+        last_node.append(node, arena);
         last_node = node;
     }
 
     println!("Parallel iteration over arena tree");
-    let _: Vec<f64> = arena.par_iter().map(|ref mut i| (i.data as f64).sqrt()).collect();
+    let _: Vec<f64> = arena.par_iter().map(|(_, node)| (node.data as f64).sqrt()).collect();
+
+    println!("Copying the arena tree");
+    let copy = arena.extract_subtree(root);
+    assert_eq!(copy.len(), arena.len());
 }
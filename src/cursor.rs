@@ -0,0 +1,151 @@
+use {DefaultIx, IndexType, Node, NodeId, Tree, Walker};
+
+macro_rules! impl_cursor_navigation {
+    ($name:ident) => {
+        impl<'a, T, Ix: IndexType> $name<'a, T, Ix> {
+            /// Return the id of the node under the cursor.
+            pub fn node_id(&self) -> NodeId<Ix> {
+                self.node
+            }
+
+            /// Return a reference to the node under the cursor.
+            pub fn node(&self) -> &Node<T, Ix> {
+                &self.tree[self.node]
+            }
+
+            /// Move to the given node if there is one, returning whether the cursor moved.
+            fn goto(&mut self, node: Option<NodeId<Ix>>) -> bool {
+                match node {
+                    Some(node) => {
+                        self.node = node;
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            /// Move to the parent of the current node, unless it is a root.
+            pub fn goto_parent(&mut self) -> bool {
+                let node = self.tree[self.node].parent;
+                self.goto(node)
+            }
+
+            /// Move to the first child of the current node, unless it is a leaf.
+            pub fn goto_first_child(&mut self) -> bool {
+                let node = self.tree[self.node].first_child;
+                self.goto(node)
+            }
+
+            /// Move to the last child of the current node, unless it is a leaf.
+            pub fn goto_last_child(&mut self) -> bool {
+                let node = self.tree[self.node].last_child;
+                self.goto(node)
+            }
+
+            /// Move to the next sibling of the current node, unless it is the last one.
+            pub fn goto_next_sibling(&mut self) -> bool {
+                let node = self.tree[self.node].next_sibling;
+                self.goto(node)
+            }
+
+            /// Move to the previous sibling of the current node, unless it is the first one.
+            pub fn goto_previous_sibling(&mut self) -> bool {
+                let node = self.tree[self.node].previous_sibling;
+                self.goto(node)
+            }
+
+            /// Move to the child of the current node at the given position, counting from zero,
+            /// unless there are not enough children.
+            pub fn goto_nth_child(&mut self, n: usize) -> bool {
+                let node = self.node.children(&*self.tree).iter(&*self.tree).nth(n);
+                self.goto(node)
+            }
+        }
+    };
+}
+
+/// A position in a `Tree`, which can move along the links between nodes
+pub struct TreeCursor<'a, T: 'a, Ix: 'a + IndexType = DefaultIx> {
+    tree: &'a Tree<T, Ix>,
+    node: NodeId<Ix>,
+}
+
+impl<'a, T, Ix: IndexType> TreeCursor<'a, T, Ix> {
+    pub(crate) fn new(tree: &'a Tree<T, Ix>, node: NodeId<Ix>) -> Self {
+        TreeCursor { tree, node }
+    }
+
+    /// Return the tree the cursor moves in.
+    pub fn tree(&self) -> &'a Tree<T, Ix> {
+        self.tree
+    }
+}
+
+impl<'a, T, Ix: IndexType> Clone for TreeCursor<'a, T, Ix> {
+    fn clone(&self) -> Self {
+        TreeCursor::new(self.tree, self.node)
+    }
+}
+
+impl_cursor_navigation!(TreeCursor);
+
+/// A position in a `Tree`, which can move along the links between nodes and edit the tree around
+/// it
+pub struct TreeCursorMut<'a, T: 'a, Ix: 'a + IndexType = DefaultIx> {
+    tree: &'a mut Tree<T, Ix>,
+    node: NodeId<Ix>,
+}
+
+impl<'a, T, Ix: IndexType> TreeCursorMut<'a, T, Ix> {
+    pub(crate) fn new(tree: &'a mut Tree<T, Ix>, node: NodeId<Ix>) -> Self {
+        TreeCursorMut { tree, node }
+    }
+
+    /// Return the tree the cursor moves in.
+    pub fn tree(&self) -> &Tree<T, Ix> {
+        self.tree
+    }
+
+    /// Return a read-only cursor at the current node.
+    pub fn as_cursor(&self) -> TreeCursor<'_, T, Ix> {
+        TreeCursor::new(self.tree, self.node)
+    }
+
+    /// Return a mutable reference to the node under the cursor.
+    pub fn node_mut(&mut self) -> &mut Node<T, Ix> {
+        &mut self.tree[self.node]
+    }
+
+    /// Insert a new node after the current node and return its id. The cursor does not move.
+    pub fn insert_after(&mut self, data: T) -> NodeId<Ix> {
+        let new_sibling = self.tree.new_node(data);
+        self.node.insert_after(new_sibling, self.tree);
+        new_sibling
+    }
+
+    /// Insert a new node before the current node and return its id. The cursor does not move.
+    pub fn insert_before(&mut self, data: T) -> NodeId<Ix> {
+        let new_sibling = self.tree.new_node(data);
+        self.node.insert_before(new_sibling, self.tree);
+        new_sibling
+    }
+
+    /// Remove the current node like `Tree::remove`, so that its children take its place.
+    ///
+    /// The cursor moves to the first of these children, or else to the next sibling, the previous
+    /// sibling or the parent of the node, whichever exists first. If none of them exists, the node
+    /// is not removed and `None` is returned.
+    pub fn remove(&mut self) -> Option<T> {
+        let node = &self.tree[self.node];
+        let next = node
+            .first_child
+            .or(node.next_sibling)
+            .or(node.previous_sibling)
+            .or(node.parent)?;
+        let data = self.tree.remove(self.node);
+        self.node = next;
+        data
+    }
+}
+
+impl_cursor_navigation!(TreeCursorMut);
@@ -0,0 +1,38 @@
+use std::{error, fmt};
+
+/// The reasons why a structural operation on a `Tree` can be refused
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum NodeError {
+    /// The operation needs two distinct nodes, but got the same node twice.
+    SameNode,
+
+    /// The node has been removed from the tree.
+    Removed,
+
+    /// The node id points past the end of the tree.
+    OutOfBounds,
+
+    /// The operation would move a node into its own subtree.
+    WouldCreateCycle,
+
+    /// The node id has not been handed out by this tree.
+    NotInThisTree,
+
+    /// The nodes are not a range of siblings, from the first one to the last one.
+    NotSiblings,
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NodeError::SameNode => write!(f, "a node can not be related to itself"),
+            NodeError::Removed => write!(f, "the node has been removed"),
+            NodeError::OutOfBounds => write!(f, "the node id is out of bounds"),
+            NodeError::WouldCreateCycle => write!(f, "the node would become its own descendant"),
+            NodeError::NotInThisTree => write!(f, "the node id belongs to another tree"),
+            NodeError::NotSiblings => write!(f, "the nodes are not a range of siblings"),
+        }
+    }
+}
+
+impl error::Error for NodeError {}
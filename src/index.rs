@@ -0,0 +1,70 @@
+use std::fmt;
+use std::hash::Hash;
+use std::num::{NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize};
+
+/// The default integer type for node indices of a `Tree`
+pub type DefaultIx = u32;
+
+// Taken and adapted from https://github.com/bluss/petgraph
+/// The unsigned integer type used for the node indices of a `Tree`
+///
+/// It limits the number of slots a `Tree` can have to one less than its maximum value. Slot
+/// generations are stored with the same width and wrap around at its maximum value.
+pub trait IndexType: Copy + Default + Hash + Ord + fmt::Debug + Send + Sync + 'static {
+    /// The non-zero counterpart of the type, which lets `Option<NodeId>` use its niche.
+    type NonZero: Copy + Hash + Ord + fmt::Debug + Send + Sync + 'static;
+
+    /// Convert from `usize`, which has to fit into the type.
+    fn new(x: usize) -> Self;
+
+    /// Convert to `usize`.
+    fn index(self) -> usize;
+
+    /// Return the maximum value of the type.
+    fn max_value() -> Self;
+
+    /// Convert to the non-zero counterpart, unless the value is zero.
+    fn to_non_zero(self) -> Option<Self::NonZero>;
+
+    /// Convert from the non-zero counterpart.
+    fn from_non_zero(x: Self::NonZero) -> Self;
+}
+
+macro_rules! impl_index_type {
+    ($ix:ty, $non_zero:ty) => {
+        impl IndexType for $ix {
+            type NonZero = $non_zero;
+
+            #[inline]
+            fn new(x: usize) -> Self {
+                debug_assert!(x <= <$ix>::MAX as usize);
+                x as $ix
+            }
+
+            #[inline]
+            fn index(self) -> usize {
+                self as usize
+            }
+
+            #[inline]
+            fn max_value() -> Self {
+                <$ix>::MAX
+            }
+
+            #[inline]
+            fn to_non_zero(self) -> Option<$non_zero> {
+                <$non_zero>::new(self)
+            }
+
+            #[inline]
+            fn from_non_zero(x: $non_zero) -> Self {
+                x.get()
+            }
+        }
+    };
+}
+
+impl_index_type!(u16, NonZeroU16);
+impl_index_type!(u32, NonZeroU32);
+impl_index_type!(u64, NonZeroU64);
+impl_index_type!(usize, NonZeroUsize);
@@ -7,7 +7,7 @@
 //!
 //! # Example usage
 //! ```
-//! use indextree::Tree;
+//! use indextree::{Tree, Walker};
 //!
 //! // Create a new arena
 //! let arena = &mut Tree::new();
@@ -18,7 +18,7 @@
 //!
 //! // Append b to a
 //! a.append(b, arena);
-//! assert_eq!(b.ancestors(arena).into_iter().count(), 2);
+//! assert_eq!(b.ancestors().iter(arena).count(), 2);
 //! ```
 #[cfg(feature = "deser")]
 extern crate serde;
@@ -30,37 +30,165 @@ extern crate rayon;
 
 #[cfg(feature = "par_iter")]
 use rayon::prelude::*;
-use std::ops::{Index, IndexMut};
-use std::{fmt, mem};
-use std::collections::VecDeque;
-pub use walker::{Walker, WalkerIter};
-
+use std::ops::{Bound, Index, IndexMut, Range, RangeFrom};
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "deser")]
+use std::convert::TryFrom;
+#[cfg(debug_assertions)]
+use std::sync::atomic::{self, AtomicUsize};
+use std::{cmp, fmt, iter, mem, slice, vec};
+use std::collections::{btree_set, BTreeSet, HashMap, VecDeque};
+pub use cursor::{TreeCursor, TreeCursorMut};
+pub use error::NodeError;
+pub use index::{DefaultIx, IndexType};
+pub use walker::{Data, DoubleEndedWalker, FusedWalker, Walker, WalkerIter, WithData};
+
+pub mod cursor;
+pub mod error;
+pub mod index;
 pub mod walker;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Hash)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
 #[cfg_attr(feature = "deser", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "deser",
+    serde(
+        into = "RawNodeId<Ix>",
+        try_from = "RawNodeId<Ix>",
+        bound(serialize = "Ix: serde::Serialize", deserialize = "Ix: serde::Deserialize<'de>")
+    )
+)]
 /// A node identifier within a particular `Tree`
-pub struct NodeId {
-    index: usize,
+///
+/// Besides the index into the arena, every identifier carries the generation of the slot it was
+/// handed out for. Slots get a new generation whenever they are released, so an identifier kept
+/// around after its node was removed will never resolve to the unrelated node which reuses the
+/// slot later on.
+///
+/// In debug builds identifiers are also branded with the `Tree` which handed them out, so that
+/// using them with another `Tree` is caught as well.
+pub struct NodeId<Ix: IndexType = DefaultIx> {
+    // One more than the index, to give `Option<NodeId<Ix>>` a niche.
+    index: Ix::NonZero,
+    generation: Ix,
+    brand: Brand,
+}
+
+#[cfg(feature = "deser")]
+#[derive(Deserialize, Serialize)]
+/// The serialized form of a `NodeId`, which leaves out the brand
+struct RawNodeId<Ix> {
+    index: Ix,
+    generation: Ix,
+}
+
+#[cfg(feature = "deser")]
+impl<Ix: IndexType> From<NodeId<Ix>> for RawNodeId<Ix> {
+    fn from(id: NodeId<Ix>) -> Self {
+        RawNodeId {
+            index: Ix::new(id.index()),
+            generation: id.generation,
+        }
+    }
+}
+
+#[cfg(feature = "deser")]
+impl<Ix: IndexType> TryFrom<RawNodeId<Ix>> for NodeId<Ix> {
+    type Error = &'static str;
+
+    fn try_from(id: RawNodeId<Ix>) -> Result<Self, Self::Error> {
+        if id.index == Ix::max_value() {
+            return Err("node index out of range");
+        }
+        Ok(NodeId::from_parts(id.index.index(), id.generation, Brand::default()))
+    }
+}
+
+impl<Ix: IndexType> fmt::Debug for NodeId<Ix> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeId")
+            .field("index", &self.index())
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+#[cfg(debug_assertions)]
+static NEXT_TREE_BRAND: AtomicUsize = AtomicUsize::new(1);
+
+#[derive(Copy, Clone, Debug, Default)]
+/// The `Tree` a `NodeId<Ix>` has been handed out by, only tracked in debug builds
+///
+/// The default brand of deserialized identifiers matches every `Tree`.
+struct Brand {
+    #[cfg(debug_assertions)]
+    tree: usize,
+}
+
+// Brands only guard against mixing up trees, they never make two nodes or trees distinct.
+impl PartialEq for Brand {
+    fn eq(&self, _other: &Brand) -> bool {
+        true
+    }
+}
+
+impl Eq for Brand {}
+
+impl PartialOrd for Brand {
+    fn partial_cmp(&self, other: &Brand) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Brand {
+    fn cmp(&self, _other: &Brand) -> cmp::Ordering {
+        cmp::Ordering::Equal
+    }
+}
+
+impl Hash for Brand {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl Brand {
+    /// Create a brand distinct from all brands created before.
+    fn new() -> Brand {
+        Brand {
+            #[cfg(debug_assertions)]
+            tree: NEXT_TREE_BRAND.fetch_add(1, atomic::Ordering::Relaxed),
+        }
+    }
+
+    /// Check whether identifiers of both brands may belong to the same `Tree`.
+    #[cfg(debug_assertions)]
+    fn matches(self, other: Brand) -> bool {
+        self.tree == 0 || other.tree == 0 || self.tree == other.tree
+    }
+
+    /// Check whether identifiers of both brands may belong to the same `Tree`.
+    #[cfg(not(debug_assertions))]
+    fn matches(self, _other: Brand) -> bool {
+        true
+    }
 }
 
 #[derive(PartialEq, Clone, Debug)]
 #[cfg_attr(feature = "deser", derive(Deserialize, Serialize))]
 /// A node within a particular `Tree`
-pub struct Node<T> {
+pub struct Node<T, Ix: IndexType = DefaultIx> {
     // Keep these private (with read-only accessors) so that we can keep them consistent.
     // E.g. the parent of a node’s child is that node.
-    parent: Option<NodeId>,
-    previous_sibling: Option<NodeId>,
-    next_sibling: Option<NodeId>,
-    first_child: Option<NodeId>,
-    last_child: Option<NodeId>,
+    parent: Option<NodeId<Ix>>,
+    previous_sibling: Option<NodeId<Ix>>,
+    next_sibling: Option<NodeId<Ix>>,
+    first_child: Option<NodeId<Ix>>,
+    last_child: Option<NodeId<Ix>>,
 
     /// The actual data which will be stored within the tree
     pub data: T,
 }
 
-impl<T> fmt::Display for Node<T> {
+impl<T, Ix: IndexType> fmt::Display for Node<T, Ix> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Parent: {:?}, ", self.parent)?;
         write!(f, "Previous sibling: {:?}, ", self.previous_sibling)?;
@@ -70,399 +198,1743 @@ impl<T> fmt::Display for Node<T> {
     }
 }
 
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "deser", derive(Deserialize, Serialize))]
+/// A single storage slot of a `Tree` together with its current generation
+struct Slot<T, Ix: IndexType> {
+    generation: Ix,
+    // `None` while the slot is vacant and waiting in `Tree::orphaned_nodes` to be reused.
+    node: Option<Node<T, Ix>>,
+}
+
+impl<T, Ix: IndexType> Slot<T, Ix> {
+    /// Return the node in this slot together with its id, unless the slot is vacant.
+    fn get(&self, index: usize, brand: Brand) -> Option<(NodeId<Ix>, &Node<T, Ix>)> {
+        let generation = self.generation;
+        self.node.as_ref().map(|node| (NodeId::from_parts(index, generation, brand), node))
+    }
+
+    /// Return the node in this slot together with its id, unless the slot is vacant.
+    fn get_mut(&mut self, index: usize, brand: Brand) -> Option<(NodeId<Ix>, &mut Node<T, Ix>)> {
+        let generation = self.generation;
+        self.node.as_mut().map(|node| (NodeId::from_parts(index, generation, brand), node))
+    }
+}
+
 #[derive(PartialEq, Clone, Debug)]
 #[cfg_attr(feature = "deser", derive(Deserialize, Serialize))]
 /// An `Tree` structure containing certain Nodes
-pub struct Tree<T> {
-    nodes: Vec<Node<T>>,
-    orphaned_nodes: VecDeque<NodeId>,
+pub struct Tree<T, Ix: IndexType = DefaultIx> {
+    nodes: Vec<Slot<T, Ix>>,
+    // Vacant slots, the most recently freed one last.
+    orphaned_nodes: Vec<NodeId<Ix>>,
+    // The generation given to newly pushed slots. It is raised above every generation ever handed
+    // out whenever slots are dropped from the end of `nodes`, so that identifiers of dropped slots
+    // can not match the slots pushed in their place.
+    first_generation: Ix,
+    // Whether every subtree is stored contiguously in tree order, which is established by
+    // `Tree::relayout` and lost on the next structural change.
+    preorder_layout: bool,
+    // All nodes without a parent.
+    roots: BTreeSet<NodeId<Ix>>,
+    primary_root: Option<NodeId<Ix>>,
+    #[cfg_attr(feature = "deser", serde(skip, default = "Brand::new"))]
+    brand: Brand,
 }
 
-impl<T> Tree<T> {
-    /// Create a new empty `Tree`
-    pub fn new() -> Tree<T> {
+impl<T, Ix: IndexType> Default for Tree<T, Ix> {
+    fn default() -> Self {
         Tree {
             nodes: Vec::new(),
-            orphaned_nodes: VecDeque::new(),
+            orphaned_nodes: Vec::new(),
+            first_generation: Ix::default(),
+            preorder_layout: true,
+            roots: BTreeSet::new(),
+            primary_root: None,
+            brand: Brand::new(),
         }
     }
+}
+
+impl<T> Tree<T> {
+    /// Create a new empty `Tree`
+    ///
+    /// Use `Tree::default` to create a `Tree` with another index type.
+    pub fn new() -> Tree<T> {
+        Self::default()
+    }
+
+    /// Create a new empty `Tree` with room for at least `capacity` nodes
+    ///
+    /// Use `Tree::default` followed by `Tree::reserve` to do the same with another index type.
+    pub fn with_capacity(capacity: usize) -> Tree<T> {
+        let mut tree = Self::default();
+        tree.reserve(capacity);
+        tree
+    }
+}
+
+impl<T, Ix: IndexType> Tree<T, Ix> {
 
     /// Create a new node from its associated data.
-    pub fn new_node(&mut self, data: T) -> NodeId {
-        let node = Node {
-            parent: None,
-            first_child: None,
-            last_child: None,
-            previous_sibling: None,
-            next_sibling: None,
-            data: data,
+    pub fn new_node(&mut self, data: T) -> NodeId<Ix> {
+        let node = Node::new(data);
+        let id = if let Some(vacant_id) = self.orphaned_nodes.pop() {
+            self.preorder_layout = false;
+            let slot = &mut self.nodes[vacant_id.index()];
+            debug_assert_eq!(slot.generation, vacant_id.generation);
+            debug_assert!(slot.node.is_none());
+            slot.node = Some(node);
+            vacant_id
+        } else {
+            self.push_node(node)
         };
+        self.roots.insert(id);
+        id
+    }
 
-        if let Some(vacant_index) = self.orphaned_nodes.pop_back() {
-            self.nodes[vacant_index.index] = node;
-            vacant_index
-        } else {
-            let next_index = self.nodes.len();
-            self.nodes.push(node);
-            NodeId { index: next_index }
+    /// Create a new node for every item of the iterator, with consecutive ids.
+    ///
+    /// Unlike `new_node`, this never reuses the slots of removed nodes, all new nodes are placed
+    /// behind the last slot of the arena.
+    pub fn new_nodes<I: IntoIterator<Item = T>>(&mut self, data: I) -> NodeRange<Ix> {
+        let data = data.into_iter();
+        self.nodes.reserve(data.size_hint().0);
+        let start = self.nodes.len();
+        for data in data {
+            let id = self.push_node(Node::new(data));
+            self.roots.insert(id);
+        }
+        NodeRange {
+            indices: start..self.nodes.len(),
+            generation: self.first_generation,
+            brand: self.brand,
+        }
+    }
+
+    /// Put the node into a new slot at the end of the arena.
+    fn push_node(&mut self, node: Node<T, Ix>) -> NodeId<Ix> {
+        // Indices are stored with an offset of one.
+        if self.nodes.len() >= Ix::max_value().index() {
+            panic!("Tree can not hold more than {} nodes", Ix::max_value().index());
         }
+        let id = NodeId::from_parts(self.nodes.len(), self.first_generation, self.brand);
+        self.nodes.push(Slot {
+            generation: id.generation,
+            node: Some(node),
+        });
+        id
+    }
+
+    /// Count the nodes in the arena, without the slots of removed nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.orphaned_nodes.len()
     }
 
     // Count nodes in arena.
     pub fn count(&self) -> usize {
-        self.nodes.len()
+        self.len()
     }
 
     // Returns true if arena has no nodes, false otherwise
     pub fn is_empty(&self) -> bool {
-        self.count() == 0
+        self.len() == 0
     }
 
-    /// Get a reference to the node with the given id if in the arena, None otherwise.
-    pub fn get(&self, id: NodeId) -> Option<&Node<T>> {
-        self.nodes.get(id.index)
+    /// Return the number of nodes the arena can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
     }
 
-    /// Get a mutable reference to the node with the given id if in the arena, None otherwise.
-    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node<T>> {
-        self.nodes.get_mut(id.index)
+    /// Reserve room for at least `additional` more nodes behind the last slot of the arena.
+    ///
+    /// The slots of removed nodes are not counted, since `new_nodes` never reuses them.
+    pub fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
     }
 
-    /// Iterate over all nodes in the arena in storage-order.
-    pub fn iter(&self) -> std::slice::Iter<Node<T>> {
-        self.nodes.iter()
+    /// Shrink the storage of the arena as much as possible.
+    ///
+    /// The slots of removed nodes are kept, use `Tree::compact` to get rid of them first.
+    pub fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        self.orphaned_nodes.shrink_to_fit();
     }
 
-    pub fn clear(&mut self) {
-        self.nodes.clear();
-        self.orphaned_nodes.clear();
+    /// Get a reference to the node with the given id if in the arena, None otherwise.
+    ///
+    /// Identifiers of removed nodes are not in the arena anymore, even if their slot has been
+    /// reused in the meantime.
+    pub fn get(&self, id: NodeId<Ix>) -> Option<&Node<T, Ix>> {
+        match self.nodes.get(id.index()) {
+            Some(slot) if slot.generation == id.generation && self.brand.matches(id.brand) => {
+                slot.node.as_ref()
+            }
+            _ => None,
+        }
     }
 
-    fn orphan_node(&mut self, id: NodeId) {
-        let mut children = id.traverse(); // TODO: Use Traverse walker
-        while let Some(child_edge) = children.walk_next(self) {
-            match child_edge {
-                NodeEdge::Start(node_id) |
-                NodeEdge::End(node_id) => {
-                    self.orphaned_nodes.push_back(id);
-                }
+    /// Get a mutable reference to the node with the given id if in the arena, None otherwise.
+    ///
+    /// Identifiers of removed nodes are not in the arena anymore, even if their slot has been
+    /// reused in the meantime.
+    pub fn get_mut(&mut self, id: NodeId<Ix>) -> Option<&mut Node<T, Ix>> {
+        match self.nodes.get_mut(id.index()) {
+            Some(slot) if slot.generation == id.generation && self.brand.matches(id.brand) => {
+                slot.node.as_mut()
             }
+            _ => None,
         }
     }
-}
 
-impl<T: Clone> Tree<T> {
-    // TODO: Move this methods to NodeId or move NodeId methods to Tree.
-    fn append_subtree_from(&mut self, from_id: NodeId, to_id: NodeId, from: &Tree<T>) {
-        let new_root_id = self.new_node(from[from_id].data.clone());
-        to_id.append(new_root_id, self);
+    /// Return the id of the node stored at the given index of the arena, if there is one.
+    ///
+    /// Indices are the positions in storage-order, including the slots of removed nodes.
+    pub fn node_id(&self, index: usize) -> Option<NodeId<Ix>> {
+        self.nodes.get(index)?.get(index, self.brand).map(|(id, _)| id)
+    }
 
-        // TODO: Use the Traverse walker here.
-        for child_id in from_id.children(from).iter(from) {
-            let new_node = self.new_node(from[child_id].data.clone());
-            new_root_id.append(new_node, self);
-            self.append_subtree_from(child_id, new_node, from);
+    /// Return a cursor positioned at the given node.
+    ///
+    /// Panics if the node is not in the arena.
+    pub fn cursor(&self, id: NodeId<Ix>) -> TreeCursor<'_, T, Ix> {
+        if self.get(id).is_none() {
+            self.invalid_node_id(id);
         }
+        TreeCursor::new(self, id)
     }
 
-    pub fn extract_subtree(&self, id: NodeId) -> Tree<T> {
-        let mut new_tree = Tree::new();
-        self.extract_subtree_into(id, &mut new_tree);
-        new_tree
+    /// Return a cursor positioned at the given node, which can also edit the arena.
+    ///
+    /// Panics if the node is not in the arena.
+    pub fn cursor_mut(&mut self, id: NodeId<Ix>) -> TreeCursorMut<'_, T, Ix> {
+        if self.get(id).is_none() {
+            self.invalid_node_id(id);
+        }
+        TreeCursorMut::new(self, id)
     }
 
-    pub fn extract_subtree_into(&self, id: NodeId, tree: &mut Tree<T>) {
-        let new_root_id = tree.new_node(self[id].data.clone());
-        tree.append_subtree_from(id, new_root_id, self);
+    /// Iterate over all nodes in the arena and their ids in storage-order.
+    pub fn iter(&self) -> Iter<'_, T, Ix> {
+        Iter((0..).zip(self.nodes.iter()), self.brand)
     }
-}
 
-#[cfg(feature = "par_iter")]
-impl<T: Sync> Tree<T> {
-    /// Return an parallel iterator over the whole arena.
-    pub fn par_iter(&self) -> rayon::slice::Iter<Node<T>> {
-        self.nodes.par_iter()
+    /// Iterate mutably over all nodes in the arena and their ids in storage-order.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, Ix> {
+        IterMut((0..).zip(self.nodes.iter_mut()), self.brand)
     }
-}
-
-trait GetPairMut<T> {
-    /// Get mutable references to two distinct nodes. Panics if the two given IDs are the same.
-    fn get_pair_mut(&mut self, a: usize, b: usize, same_index_error_message: &'static str) -> (&mut T, &mut T);
-}
 
-impl<T> GetPairMut<T> for Vec<T> {
-    fn get_pair_mut(&mut self, a: usize, b: usize, same_index_error_message: &'static str) -> (&mut T, &mut T) {
-        if a == b {
-            panic!(same_index_error_message)
+    /// Iterate over a node and its descendants in tree order, straight from the storage of the
+    /// arena.
+    ///
+    /// This is only possible if the subtree is stored contiguously, which is the case right after
+    /// `Tree::relayout` until nodes are moved around or removed. Returns `None` otherwise or if
+    /// the node is not in the arena.
+    pub fn subtree_iter(&self, id: NodeId<Ix>) -> Option<Iter<'_, T, Ix>> {
+        if !self.preorder_layout {
+            return None;
         }
-        let (xs, ys) = self.split_at_mut(std::cmp::max(a, b));
-        if a < b {
-            (&mut xs[a], &mut ys[0])
-        } else {
-            (&mut ys[0], &mut xs[b])
+        let mut last = id;
+        while let Some(last_child) = self.get(last)?.last_child {
+            last = last_child;
         }
+        let slots = self.nodes[id.index()..=last.index()].iter();
+        Some(Iter((id.index()..).zip(slots), self.brand))
     }
-}
 
-impl<T> Index<NodeId> for Tree<T> {
-    type Output = Node<T>;
-
-    fn index(&self, node: NodeId) -> &Node<T> {
-        &self.nodes[node.index]
+    pub fn clear(&mut self) {
+        if let Some(generation) = self.nodes.iter().map(|slot| slot.generation).max() {
+            self.first_generation = self.first_generation.max(next_generation(generation));
+        }
+        self.nodes.clear();
+        self.orphaned_nodes.clear();
+        self.preorder_layout = true;
+        self.roots.clear();
+        self.primary_root = None;
     }
-}
 
-impl<T> IndexMut<NodeId> for Tree<T> {
-    fn index_mut(&mut self, node: NodeId) -> &mut Node<T> {
-        &mut self.nodes[node.index]
+    /// Return an iterator over all nodes without a parent, in storage-order.
+    pub fn roots(&self) -> Roots<'_, Ix> {
+        Roots(self.roots.iter())
     }
-}
 
-impl<T> Node<T> {
-    /// Return the ID of the parent node, unless this node is the root of the tree.
-    pub fn parent(&self) -> Option<NodeId> {
-        self.parent
+    /// Return the primary root of the arena.
+    ///
+    /// This is the node designated by `Tree::set_root` as long as it has no parent, otherwise the
+    /// first node in `Tree::roots`.
+    pub fn root(&self) -> Option<NodeId<Ix>> {
+        match self.primary_root {
+            Some(root) if self.roots.contains(&root) => Some(root),
+            _ => self.roots().next(),
+        }
     }
 
-    /// Return the ID of the first child of this node, unless it has no child.
-    pub fn first_child(&self) -> Option<NodeId> {
-        self.first_child
+    /// Designate the primary root of the arena, which is returned by `Tree::root` as long as it
+    /// has no parent.
+    pub fn set_root(&mut self, id: NodeId<Ix>) -> Result<(), NodeError> {
+        self.check_node(id)?;
+        self.primary_root = Some(id);
+        Ok(())
     }
 
-    /// Return the ID of the last child of this node, unless it has no child.
-    pub fn last_child(&self) -> Option<NodeId> {
-        self.last_child
+    /// Return an iterator of references to all nodes in the arena, in tree order.
+    ///
+    /// The trees are traversed one after another, in the order of `Tree::roots`.
+    pub fn forest_traverse(&self) -> ForestTraverse<Ix> {
+        let root = self.roots().next();
+        ForestTraverse {
+            root,
+            traverse: root.map(NodeId::traverse),
+        }
     }
 
-    /// Return the ID of the previous sibling of this node, unless it is a first child.
-    pub fn previous_sibling(&self) -> Option<NodeId> {
-        self.previous_sibling
+    /// Keep track of whether the node is a root after its parent might have changed.
+    fn update_root(&mut self, id: NodeId<Ix>) {
+        if self[id].parent.is_none() {
+            self.roots.insert(id);
+        } else {
+            self.roots.remove(&id);
+        }
     }
 
-    /// Return the ID of the previous sibling of this node, unless it is a first child.
-    pub fn next_sibling(&self) -> Option<NodeId> {
-        self.next_sibling
+    /// Remove a single node from the arena and return its data, or `None` if the node is not in
+    /// the arena.
+    ///
+    /// The children of the removed node take its place within its parent and siblings.
+    pub fn remove(&mut self, id: NodeId<Ix>) -> Option<T> {
+        self.get(id)?;
+        if let (Some(first_child), Some(last_child)) = (self[id].first_child, self[id].last_child) {
+            // The children are descendants of `id`, so they can be relinked without checks.
+            NodeId::unlink_range(first_child, last_child, self);
+            let (parent, previous_sibling) = (self[id].parent, self[id].previous_sibling);
+            NodeId::link_range(
+                first_child,
+                last_child,
+                Some(id),
+                parent,
+                previous_sibling,
+                Some(id),
+                self,
+            );
+        }
+        id.detach(self);
+        Some(self.free_node(id))
     }
-}
 
-impl NodeId {
-    /// Create a `NodeId` used for attempting to get `Node`s references from an `Tree`.
-    pub fn new(index: usize) -> Self {
-        Self { index }
+    /// Remove a node together with all its descendants from the arena.
+    ///
+    /// Returns the identifiers the nodes had and their data in tree order, or an empty `Vec` if
+    /// the node is not in the arena.
+    pub fn remove_subtree(&mut self, id: NodeId<Ix>) -> Vec<(NodeId<Ix>, T)> {
+        if self.get(id).is_none() {
+            return Vec::new();
+        }
+        id.detach(self);
+        let ids = id.descendants().iter(self).collect::<Vec<_>>();
+        ids.into_iter().map(|id| (id, self.free_node(id))).collect()
     }
 
-    /// Return an iterator of references to this node and its ancestors.
+    /// Move the nodes to the front of the arena to get rid of the slots of removed nodes.
     ///
-    /// Call `.next().unwrap()` once on the iterator to skip the node itself.
-    pub fn ancestors(self) -> Ancestors {
-        Ancestors {
-            node: Some(self),
+    /// The nodes keep their storage-order, but most of them get new ids in the process. The
+    /// returned map contains the new id of every node in the arena by its old id.
+    pub fn compact(&mut self) -> HashMap<NodeId<Ix>, NodeId<Ix>> {
+        let mut remap = HashMap::with_capacity(self.len());
+        let mut new_ids = Vec::with_capacity(self.nodes.len());
+        for (index, slot) in self.nodes.iter().enumerate() {
+            new_ids.push(slot.get(index, self.brand).map(|(id, _)| {
+                let new_id = self.relocated_id(index, remap.len());
+                remap.insert(id, new_id);
+                new_id
+            }));
         }
+        self.relocate(&new_ids);
+        remap
     }
 
-    /// Return an iterator of references to this node and the siblings before it.
+    /// Reorder the arena so that every subtree is stored contiguously in tree order.
     ///
-    /// Call `.next().unwrap()` once on the iterator to skip the node itself.
-    pub fn preceding_siblings(self) -> PrecedingSiblings {
-        PrecedingSiblings {
-            node: Some(self),
+    /// Trees follow each other in the storage-order of their roots, which makes traversals a
+    /// linear scan and enables `Tree::subtree_iter`. Node ids change in the process, the returned
+    /// map contains the new id of every node in the arena by its old id.
+    pub fn relayout(&mut self) -> HashMap<NodeId<Ix>, NodeId<Ix>> {
+        let mut remap = HashMap::with_capacity(self.len());
+        let mut new_ids = vec![None; self.nodes.len()];
+        for (first, node) in self.iter() {
+            // Detached siblings are laid out together with the first one of them.
+            if node.parent.is_some() || node.previous_sibling.is_some() {
+                continue;
+            }
+            for root in first.following_siblings().iter(self) {
+                for id in root.descendants().iter(self) {
+                    let new_id = self.relocated_id(id.index(), remap.len());
+                    new_ids[id.index()] = Some(new_id);
+                    remap.insert(id, new_id);
+                }
+            }
         }
+        self.relocate(&new_ids);
+        self.preorder_layout = true;
+        remap
     }
 
-    /// Return an iterator of references to this node and the siblings after it.
+    /// Return the id a node gets when it is moved from slot `from` to slot `to`.
     ///
-    /// Call `.next().unwrap()` once on the iterator to skip the node itself.
-    pub fn following_siblings(self) -> FollowingSiblings {
-        FollowingSiblings {
-            node: Some(self),
+    /// Moving a node into an occupied slot has to skip the generation of the previous occupant,
+    /// even if that one is moved away as well.
+    fn relocated_id(&self, from: usize, to: usize) -> NodeId<Ix> {
+        let target = &self.nodes[to];
+        let generation = if from == to || target.node.is_none() {
+            target.generation
+        } else {
+            next_generation(target.generation)
+        };
+        NodeId::from_parts(to, generation, self.brand)
+    }
+
+    /// Move every node into the slot of its new id, indexed by the old index of the node.
+    ///
+    /// The new ids have to cover the slots at the front of the arena without gaps, everything
+    /// behind them is dropped.
+    fn relocate(&mut self, new_ids: &[Option<NodeId<Ix>>]) {
+        let primary_root = match self.primary_root {
+            Some(id) if self.get(id).is_some() => new_ids[id.index()],
+            _ => None,
+        };
+        let len = new_ids.iter().filter(|id| id.is_some()).count();
+        let old_slots = mem::take(&mut self.nodes);
+        if let Some(generation) = old_slots[len..].iter().map(|slot| slot.generation).max() {
+            self.first_generation = self.first_generation.max(next_generation(generation));
         }
+
+        let mut new_slots = (0..len).map(|_| None).collect::<Vec<_>>();
+        for (slot, new_id) in old_slots.into_iter().zip(new_ids) {
+            if let (Some(mut node), &Some(new_id)) = (slot.node, new_id) {
+                node.map_links(|id| new_ids[id.index()].expect("Link to a vacant slot"));
+                new_slots[new_id.index()] = Some(Slot {
+                    generation: new_id.generation,
+                    node: Some(node),
+                });
+            }
+        }
+        self.nodes = new_slots
+            .into_iter()
+            .map(|slot| slot.expect("Gap in relocated slots"))
+            .collect();
+        self.orphaned_nodes.clear();
+        self.roots = self.roots.iter().map(|id| new_ids[id.index()].unwrap()).collect();
+        self.primary_root = primary_root;
     }
 
-    /// Return an iterator of references to this node’s children.
-    pub fn children<T>(self, arena: &Tree<T>) -> Children {
-        Children {
-            node: arena[self].first_child,
+    /// Move a node and its descendants out of `from` and into this arena, without cloning their
+    /// data.
+    ///
+    /// The moved node becomes the last child of `to_parent`, or a new root if that is `None`.
+    /// Returns the new id of the moved node, together with the new ids of all moved nodes keyed by
+    /// their ids in `from`.
+    ///
+    /// Panics if `from_id` is not in `from` or `to_parent` is not in this arena.
+    pub fn transplant(
+        &mut self,
+        from: &mut Tree<T, Ix>,
+        from_id: NodeId<Ix>,
+        to_parent: Option<NodeId<Ix>>,
+    ) -> (NodeId<Ix>, HashMap<NodeId<Ix>, NodeId<Ix>>) {
+        if let Err(err) = self.check_transfer(from, from_id, to_parent) {
+            panic!("Can not transplant node: {}", err);
+        }
+
+        let parents = from_id
+            .descendants()
+            .iter(from)
+            .map(|id| from[id].parent)
+            .collect::<Vec<_>>();
+        let mut new_ids = HashMap::with_capacity(parents.len());
+        for ((old_id, data), old_parent) in from.remove_subtree(from_id).into_iter().zip(parents) {
+            let new_id = self.new_node(data);
+            let parent = if old_id == from_id {
+                to_parent
+            } else {
+                old_parent.map(|old_parent| new_ids[&old_parent])
+            };
+            if let Some(parent) = parent {
+                parent.link_last_child(new_id, self);
+            }
+            new_ids.insert(old_id, new_id);
         }
+        (new_ids[&from_id], new_ids)
     }
 
-    /// Return an iterator of references to this node’s children, in reverse order.
-    pub fn reverse_children<T>(self, arena: &Tree<T>) -> ReverseChildren {
-        ReverseChildren {
-            node: arena[self].last_child,
+    /// Check that `from_id` is in `from` and `to_parent` is in this arena.
+    fn check_transfer(
+        &self,
+        from: &Tree<T, Ix>,
+        from_id: NodeId<Ix>,
+        to_parent: Option<NodeId<Ix>>,
+    ) -> Result<(), NodeError> {
+        from.check_node(from_id)?;
+        to_parent.map_or(Ok(()), |id| self.check_node(id))
+    }
+
+    /// Return mutable references to the data of the given distinct nodes, in the given order.
+    fn data_mut(&mut self, ids: Vec<NodeId<Ix>>) -> DataMut<'_, T> {
+        let mut order = ids
+            .into_iter()
+            .enumerate()
+            .map(|(position, id)| (id.index(), position))
+            .collect::<Vec<_>>();
+        order.sort_unstable();
+
+        // Split the borrows off the slots in storage order, then put them into the given order.
+        let mut data = order.iter().map(|_| None).collect::<Vec<_>>();
+        let mut slots = self.nodes.iter_mut();
+        let mut next_index = 0;
+        for (index, position) in order {
+            let skip = index.checked_sub(next_index).expect("Duplicate node id");
+            let slot = slots.nth(skip).expect("Node id out of bounds");
+            data[position] = Some(&mut slot.node.as_mut().expect("Vacant slot").data);
+            next_index = index + 1;
         }
+        DataMut(data.into_iter())
     }
 
-    /// Return an iterator of references to this node and its descendants, in tree order.
+    /// Take the node out of its slot and queue the slot for reuse under a new generation.
     ///
-    /// Parent nodes appear before the descendants.
-    /// Call `.next().unwrap()` once on the iterator to skip the node itself.
-    pub fn descendants(self) -> Descendants {
-        Descendants(self.traverse())
+    /// Links from and to the node are not touched, this is up to the caller.
+    fn free_node(&mut self, id: NodeId<Ix>) -> T {
+        let slot = &mut self.nodes[id.index()];
+        debug_assert_eq!(slot.generation, id.generation);
+        let node = slot.node.take().expect("Can not free a vacant slot");
+        slot.generation = next_generation(slot.generation);
+        self.orphaned_nodes.push(NodeId::from_parts(id.index(), slot.generation, self.brand));
+        self.roots.remove(&id);
+        node.data
     }
 
-    /// Return an iterator of references to this node and its descendants, in tree order.
-    pub fn traverse(self) -> Traverse {
-        Traverse {
-            root: self,
-            next: Some(NodeEdge::Start(self)),
+    /// Check whether the identifier resolves to a node of this arena.
+    fn check_node(&self, id: NodeId<Ix>) -> Result<(), NodeError> {
+        if !self.brand.matches(id.brand) {
+            return Err(NodeError::NotInThisTree);
+        }
+        match self.nodes.get(id.index()) {
+            None => Err(NodeError::OutOfBounds),
+            Some(slot) if slot.generation == id.generation && slot.node.is_some() => Ok(()),
+            Some(slot) if id.generation < slot.generation => Err(NodeError::Removed),
+            // Generations only ever grow, so this tree never handed out the identifier.
+            Some(_) => Err(NodeError::NotInThisTree),
         }
     }
 
-    /// Return an iterator of references to this node and its descendants, in tree order.
-    pub fn reverse_traverse(self) -> ReverseTraverse {
-        ReverseTraverse {
-            root: self,
-            next: Some(NodeEdge::End(self)),
+    /// Panic with a descriptive message for an identifier which does not resolve to a node.
+    fn invalid_node_id(&self, id: NodeId<Ix>) -> ! {
+        match self.check_node(id) {
+            Err(err) => panic!("Invalid node id {:?}: {}", id, err),
+            Ok(()) => unreachable!(),
         }
     }
+}
 
-    /// Detach a node from its parent and siblings. Children are not affected.
-    pub fn detach<T>(self, arena: &mut Tree<T>) {
-        let (parent, previous_sibling, next_sibling) = {
-            let node = &mut arena[self];
-            (
-                node.parent.take(),
-                node.previous_sibling.take(),
-                node.next_sibling.take(),
-            )
-        };
+/// Return the generation following the given one.
+fn next_generation<Ix: IndexType>(generation: Ix) -> Ix {
+    if generation == Ix::max_value() {
+        Ix::default()
+    } else {
+        Ix::new(generation.index() + 1)
+    }
+}
 
-        if let Some(next_sibling) = next_sibling {
-            arena[next_sibling].previous_sibling = previous_sibling;
-        } else if let Some(parent) = parent {
-            arena[parent].last_child = previous_sibling;
-        }
+/// An iterator over all nodes of a `Tree` and their ids, in storage-order.
+#[derive(Clone, Debug)]
+pub struct Iter<'a, T: 'a, Ix: 'a + IndexType = DefaultIx>(
+    iter::Zip<RangeFrom<usize>, slice::Iter<'a, Slot<T, Ix>>>,
+    Brand,
+);
 
-        if let Some(previous_sibling) = previous_sibling {
-            arena[previous_sibling].next_sibling = next_sibling;
-        } else if let Some(parent) = parent {
-            arena[parent].first_child = next_sibling;
-        }
+impl<'a, T, Ix: IndexType> Iterator for Iter<'a, T, Ix> {
+    type Item = (NodeId<Ix>, &'a Node<T, Ix>);
+
+    fn next(&mut self) -> Option<(NodeId<Ix>, &'a Node<T, Ix>)> {
+        let brand = self.1;
+        self.0.by_ref().find_map(|(index, slot)| slot.get(index, brand))
     }
 
-    /// Append a new child to this node, after existing children.
-    pub fn append<T>(self, new_child: NodeId, arena: &mut Tree<T>) {
-        new_child.detach(arena);
-        let last_child_opt;
-        {
-            let (self_borrow, new_child_borrow) =
-                arena
-                    .nodes
-                    .get_pair_mut(self.index, new_child.index, "Can not append a node to itself");
-            new_child_borrow.parent = Some(self);
-            last_child_opt = mem::replace(&mut self_borrow.last_child, Some(new_child));
-            if let Some(last_child) = last_child_opt {
-                new_child_borrow.previous_sibling = Some(last_child);
-            } else {
-                debug_assert!(self_borrow.first_child.is_none());
-                self_borrow.first_child = Some(new_child);
-            }
-        }
-        if let Some(last_child) = last_child_opt {
-            debug_assert!(arena[last_child].next_sibling.is_none());
-            arena[last_child].next_sibling = Some(new_child);
-        }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.size_hint().1)
     }
+}
 
-    /// Prepend a new child to this node, before existing children.
-    pub fn prepend<T>(self, new_child: NodeId, arena: &mut Tree<T>) {
-        new_child.detach(arena);
-        let first_child_opt;
-        {
-            let (self_borrow, new_child_borrow) =
-                arena
-                    .nodes
-                    .get_pair_mut(self.index, new_child.index, "Can not prepend a node to itself");
-            new_child_borrow.parent = Some(self);
-            first_child_opt = mem::replace(&mut self_borrow.first_child, Some(new_child));
-            if let Some(first_child) = first_child_opt {
-                new_child_borrow.next_sibling = Some(first_child);
-            } else {
-                self_borrow.last_child = Some(new_child);
-                debug_assert!(&self_borrow.first_child.is_none());
-            }
-        }
-        if let Some(first_child) = first_child_opt {
-            debug_assert!(arena[first_child].previous_sibling.is_none());
-            arena[first_child].previous_sibling = Some(new_child);
-        }
+/// An iterator over the roots of a `Tree`, in storage-order.
+#[derive(Clone, Debug)]
+pub struct Roots<'a, Ix: 'a + IndexType = DefaultIx>(btree_set::Iter<'a, NodeId<Ix>>);
+
+impl<'a, Ix: IndexType> Iterator for Roots<'a, Ix> {
+    type Item = NodeId<Ix>;
+
+    fn next(&mut self) -> Option<NodeId<Ix>> {
+        self.0.next().cloned()
     }
 
-    /// Copies and appends the root node with its descendants to this node.
-    pub fn append_subtree<T: Clone>(&mut self, from_tree: &Tree<T>, into_tree: &mut Tree<T>) {
-        let from_root_id = NodeId::new(0);
-        if let Some(_) = from_tree.get(from_root_id) {
-            into_tree.append_subtree_from(from_root_id, *self, from_tree);
-        }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
     }
+}
 
-    /// Detaches and marks the node and its children as reusable.
-    pub fn orphan<T>(self, arena: &mut Tree<T>) {
-        self.detach(arena);
-        arena.orphan_node(self);
+/// An iterator over the consecutive ids of the nodes created by `Tree::new_nodes`.
+#[derive(Clone, Debug)]
+pub struct NodeRange<Ix: IndexType = DefaultIx> {
+    indices: Range<usize>,
+    generation: Ix,
+    brand: Brand,
+}
+
+impl<Ix: IndexType> Iterator for NodeRange<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn next(&mut self) -> Option<NodeId<Ix>> {
+        let (generation, brand) = (self.generation, self.brand);
+        self.indices.next().map(|index| NodeId::from_parts(index, generation, brand))
     }
 
-    /// Insert a new sibling after this node.
-    pub fn insert_after<T>(self, new_sibling: NodeId, arena: &mut Tree<T>) {
-        new_sibling.detach(arena);
-        let next_sibling_opt;
-        let parent_opt;
-        {
-            let (self_borrow, new_sibling_borrow) =
-                arena
-                    .nodes
-                    .get_pair_mut(self.index, new_sibling.index, "Can not insert a node after itself");
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl<Ix: IndexType> DoubleEndedIterator for NodeRange<Ix> {
+    fn next_back(&mut self) -> Option<NodeId<Ix>> {
+        let (generation, brand) = (self.generation, self.brand);
+        self.indices.next_back().map(|index| NodeId::from_parts(index, generation, brand))
+    }
+}
+
+impl<Ix: IndexType> ExactSizeIterator for NodeRange<Ix> {}
+
+/// An iterator of mutable references to the data of a set of nodes.
+#[derive(Debug)]
+pub struct DataMut<'a, T: 'a>(vec::IntoIter<Option<&'a mut T>>);
+
+impl<'a, T> Iterator for DataMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.0.next().map(|data| data.expect("Missing node data"))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for DataMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        self.0.next_back().map(|data| data.expect("Missing node data"))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for DataMut<'a, T> {}
+
+/// A mutable iterator over all nodes of a `Tree` and their ids, in storage-order.
+#[derive(Debug)]
+pub struct IterMut<'a, T: 'a, Ix: 'a + IndexType = DefaultIx>(
+    iter::Zip<RangeFrom<usize>, slice::IterMut<'a, Slot<T, Ix>>>,
+    Brand,
+);
+
+impl<'a, T, Ix: IndexType> Iterator for IterMut<'a, T, Ix> {
+    type Item = (NodeId<Ix>, &'a mut Node<T, Ix>);
+
+    fn next(&mut self) -> Option<(NodeId<Ix>, &'a mut Node<T, Ix>)> {
+        let brand = self.1;
+        self.0.by_ref().find_map(|(index, slot)| slot.get_mut(index, brand))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.size_hint().1)
+    }
+}
+
+impl<T: Clone, Ix: IndexType> Tree<T, Ix> {
+    fn append_subtree_from(
+        &mut self,
+        from: &Tree<T, Ix>,
+        from_id: NodeId<Ix>,
+        to_parent: Option<NodeId<Ix>>,
+        new_ids: &mut Option<HashMap<NodeId<Ix>, NodeId<Ix>>>,
+    ) -> NodeId<Ix> {
+        // The copy of the node whose descendants are being copied.
+        let mut parent = to_parent;
+        let mut new_root_id = None;
+        for edge in from_id.traverse().iter(from) {
+            match edge {
+                NodeEdge::Start(id) => {
+                    let new_id = self.new_node(from[id].data.clone());
+                    if let Some(parent) = parent {
+                        parent.link_last_child(new_id, self);
+                    }
+                    if let Some(new_ids) = new_ids.as_mut() {
+                        new_ids.insert(id, new_id);
+                    }
+                    new_root_id.get_or_insert(new_id);
+                    parent = Some(new_id);
+                }
+                NodeEdge::End(_) => parent = parent.and_then(|parent| self[parent].parent),
+            }
+        }
+        new_root_id.expect("Traverse always starts with the root")
+    }
+
+    /// Copy a node of `from` and its descendants into this arena.
+    ///
+    /// The copy becomes the last child of `to_parent`, or a new root if that is `None`. Returns
+    /// the id of the copied node.
+    ///
+    /// Panics if `from_id` is not in `from` or `to_parent` is not in this arena.
+    pub fn copy_subtree(
+        &mut self,
+        from: &Tree<T, Ix>,
+        from_id: NodeId<Ix>,
+        to_parent: Option<NodeId<Ix>>,
+    ) -> NodeId<Ix> {
+        if let Err(err) = self.check_transfer(from, from_id, to_parent) {
+            panic!("Can not copy node: {}", err);
+        }
+        self.append_subtree_from(from, from_id, to_parent, &mut None)
+    }
+
+    /// Copy a node of `from` and its descendants into this arena, like `copy_subtree`.
+    ///
+    /// Returns the id of the copied node, together with the ids of all copies keyed by the ids of
+    /// their originals in `from`.
+    pub fn copy_subtree_with_ids(
+        &mut self,
+        from: &Tree<T, Ix>,
+        from_id: NodeId<Ix>,
+        to_parent: Option<NodeId<Ix>>,
+    ) -> (NodeId<Ix>, HashMap<NodeId<Ix>, NodeId<Ix>>) {
+        if let Err(err) = self.check_transfer(from, from_id, to_parent) {
+            panic!("Can not copy node: {}", err);
+        }
+        let mut new_ids = Some(HashMap::new());
+        let new_id = self.append_subtree_from(from, from_id, to_parent, &mut new_ids);
+        (new_id, new_ids.unwrap_or_default())
+    }
+
+    /// Copy a node and its descendants into a new arena.
+    pub fn extract_subtree(&self, id: NodeId<Ix>) -> Tree<T, Ix> {
+        let mut new_tree = Tree::default();
+        self.extract_subtree_into(id, &mut new_tree);
+        new_tree
+    }
+
+    /// Copy a node and its descendants into another arena as a new root, and return the id of
+    /// the copy.
+    pub fn extract_subtree_into(&self, id: NodeId<Ix>, tree: &mut Tree<T, Ix>) -> NodeId<Ix> {
+        tree.copy_subtree(self, id, None)
+    }
+}
+
+#[cfg(feature = "par_iter")]
+impl<T: Sync, Ix: IndexType + Sync> Tree<T, Ix> {
+    /// Return an parallel iterator over all nodes in the arena and their ids.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (NodeId<Ix>, &Node<T, Ix>)> {
+        let brand = self.brand;
+        self.nodes.par_iter().enumerate().filter_map(move |(index, slot)| slot.get(index, brand))
+    }
+}
+
+trait GetPairMut<T, Ix: IndexType> {
+    /// Get mutable references to two distinct nodes. Panics if the two given IDs are the same.
+    fn get_pair_mut(
+        &mut self,
+        a: NodeId<Ix>,
+        b: NodeId<Ix>,
+    ) -> (&mut Node<T, Ix>, &mut Node<T, Ix>);
+}
+
+impl<T, Ix: IndexType> GetPairMut<T, Ix> for Tree<T, Ix> {
+    fn get_pair_mut(
+        &mut self,
+        a: NodeId<Ix>,
+        b: NodeId<Ix>,
+    ) -> (&mut Node<T, Ix>, &mut Node<T, Ix>) {
+        if a == b {
+            panic!("Can not borrow the same node twice")
+        }
+        for &id in &[a, b] {
+            if self.get(id).is_none() {
+                self.invalid_node_id(id)
+            }
+        }
+        let (xs, ys) = self.nodes.split_at_mut(std::cmp::max(a.index(), b.index()));
+        let (a, b) = if a.index() < b.index() {
+            (&mut xs[a.index()], &mut ys[0])
+        } else {
+            (&mut ys[0], &mut xs[b.index()])
+        };
+        (a.node.as_mut().unwrap(), b.node.as_mut().unwrap())
+    }
+}
+
+impl<T, Ix: IndexType> Index<NodeId<Ix>> for Tree<T, Ix> {
+    type Output = Node<T, Ix>;
+
+    fn index(&self, node: NodeId<Ix>) -> &Node<T, Ix> {
+        match self.get(node) {
+            Some(node) => node,
+            None => self.invalid_node_id(node),
+        }
+    }
+}
+
+impl<T, Ix: IndexType> IndexMut<NodeId<Ix>> for Tree<T, Ix> {
+    fn index_mut(&mut self, node: NodeId<Ix>) -> &mut Node<T, Ix> {
+        if self.get(node).is_none() {
+            self.invalid_node_id(node)
+        }
+        self.nodes[node.index()].node.as_mut().unwrap()
+    }
+}
+
+impl<T, Ix: IndexType> Node<T, Ix> {
+    /// Return the ID of the parent node, unless this node is the root of the tree.
+    pub fn parent(&self) -> Option<NodeId<Ix>> {
+        self.parent
+    }
+
+    /// Return the ID of the first child of this node, unless it has no child.
+    pub fn first_child(&self) -> Option<NodeId<Ix>> {
+        self.first_child
+    }
+
+    /// Return the ID of the last child of this node, unless it has no child.
+    pub fn last_child(&self) -> Option<NodeId<Ix>> {
+        self.last_child
+    }
+
+    /// Return the ID of the previous sibling of this node, unless it is a first child.
+    pub fn previous_sibling(&self) -> Option<NodeId<Ix>> {
+        self.previous_sibling
+    }
+
+    /// Return the ID of the previous sibling of this node, unless it is a first child.
+    pub fn next_sibling(&self) -> Option<NodeId<Ix>> {
+        self.next_sibling
+    }
+
+    /// Create a node without any links.
+    fn new(data: T) -> Self {
+        Node {
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+            data,
+        }
+    }
+
+    /// Replace every link to another node by the result of `f`.
+    fn map_links<F: FnMut(NodeId<Ix>) -> NodeId<Ix>>(&mut self, mut f: F) {
+        for link in &mut [
+            &mut self.parent,
+            &mut self.previous_sibling,
+            &mut self.next_sibling,
+            &mut self.first_child,
+            &mut self.last_child,
+        ] {
+            if let Some(id) = link.as_mut() {
+                *id = f(*id);
+            }
+        }
+    }
+}
+
+impl<Ix: IndexType> NodeId<Ix> {
+    fn from_parts(index: usize, generation: Ix, brand: Brand) -> Self {
+        let index = Ix::new(index + 1).to_non_zero().unwrap();
+        NodeId { index, generation, brand }
+    }
+
+    /// Return the index of the node in the storage-order of its `Tree`.
+    pub fn index(self) -> usize {
+        Ix::from_non_zero(self.index).index() - 1
+    }
+
+    /// Return an iterator of references to this node and its ancestors.
+    ///
+    /// Use `strict_ancestors` to skip the node itself.
+    pub fn ancestors(self) -> Ancestors<Ix> {
+        Ancestors {
+            node: Some(self),
+        }
+    }
+
+    /// Return an iterator of references to this node and its ancestors, like `ancestors`.
+    pub fn ancestors_or_self(self) -> Ancestors<Ix> {
+        self.ancestors()
+    }
+
+    /// Return an iterator of references to the ancestors of this node, without the node itself.
+    pub fn strict_ancestors<T>(self, arena: &Tree<T, Ix>) -> Ancestors<Ix> {
+        Ancestors {
+            node: arena[self].parent,
+        }
+    }
+
+    /// Return an iterator of references to this node and the siblings before it.
+    ///
+    /// Use `strict_preceding_siblings` to skip the node itself.
+    pub fn preceding_siblings(self) -> PrecedingSiblings<Ix> {
+        PrecedingSiblings {
+            node: Some(self),
+        }
+    }
+
+    /// Return an iterator of references to this node and the siblings before it, like
+    /// `preceding_siblings`.
+    pub fn preceding_siblings_or_self(self) -> PrecedingSiblings<Ix> {
+        self.preceding_siblings()
+    }
+
+    /// Return an iterator of references to the siblings before this node, without the node itself.
+    pub fn strict_preceding_siblings<T>(self, arena: &Tree<T, Ix>) -> PrecedingSiblings<Ix> {
+        PrecedingSiblings {
+            node: arena[self].previous_sibling,
+        }
+    }
+
+    /// Return an iterator of references to this node and the siblings after it.
+    ///
+    /// Use `strict_following_siblings` to skip the node itself.
+    pub fn following_siblings(self) -> FollowingSiblings<Ix> {
+        FollowingSiblings {
+            node: Some(self),
+        }
+    }
+
+    /// Return an iterator of references to this node and the siblings after it, like
+    /// `following_siblings`.
+    pub fn following_siblings_or_self(self) -> FollowingSiblings<Ix> {
+        self.following_siblings()
+    }
+
+    /// Return an iterator of references to the siblings after this node, without the node itself.
+    pub fn strict_following_siblings<T>(self, arena: &Tree<T, Ix>) -> FollowingSiblings<Ix> {
+        FollowingSiblings {
+            node: arena[self].next_sibling,
+        }
+    }
+
+    /// Return an iterator of references to the siblings before and after this node, in tree
+    /// order and without the node itself.
+    pub fn siblings<T>(self, arena: &Tree<T, Ix>) -> Siblings<Ix> {
+        let first = match arena[self].parent {
+            Some(parent) => arena[parent].first_child,
+            None => self.preceding_siblings().iter(arena).last(),
+        };
+        Siblings { node: first, skip: self }
+    }
+
+    /// Return an iterator of references to the nodes after this node in tree order, without its
+    /// descendants.
+    pub fn following<T>(self, arena: &Tree<T, Ix>) -> Following<Ix> {
+        Following {
+            node: Following::skip_subtree(self, arena),
+        }
+    }
+
+    /// Return an iterator of references to the nodes before this node in reverse tree order,
+    /// without its ancestors.
+    pub fn preceding<T>(self, arena: &Tree<T, Ix>) -> Preceding<Ix> {
+        let mut ancestor = self;
+        Preceding {
+            node: Preceding::previous(self, &mut ancestor, arena),
+            ancestor,
+        }
+    }
+
+    /// Return an iterator of references to this node’s children.
+    pub fn children<T>(self, arena: &Tree<T, Ix>) -> Children<Ix> {
+        Children(SiblingRange::children(self, arena))
+    }
+
+    /// Return an iterator of mutable references to the data of this node’s children.
+    pub fn children_mut<T>(self, arena: &mut Tree<T, Ix>) -> DataMut<'_, T> {
+        let ids = self.children(arena).iter(arena).collect();
+        arena.data_mut(ids)
+    }
+
+    /// Return an iterator of references to this node’s children, in reverse order.
+    pub fn reverse_children<T>(self, arena: &Tree<T, Ix>) -> ReverseChildren<Ix> {
+        ReverseChildren(SiblingRange::children(self, arena))
+    }
+
+    /// Return an iterator of references to this node and its descendants, in tree order.
+    ///
+    /// Parent nodes appear before the descendants.
+    /// Use `strict_descendants` to skip the node itself.
+    pub fn descendants(self) -> Descendants<Ix> {
+        Descendants(self.traverse())
+    }
+
+    /// Return an iterator of mutable references to the data of this node and its descendants, in
+    /// tree order.
+    pub fn descendants_mut<T>(self, arena: &mut Tree<T, Ix>) -> DataMut<'_, T> {
+        let ids = self.descendants().iter(arena).collect();
+        arena.data_mut(ids)
+    }
+
+    /// Return an iterator of references to this node and its descendants in tree order, like
+    /// `descendants`.
+    pub fn descendants_or_self(self) -> Descendants<Ix> {
+        self.descendants()
+    }
+
+    /// Return an iterator of references to the descendants of this node in tree order, without
+    /// the node itself.
+    pub fn strict_descendants<T>(self, arena: &Tree<T, Ix>) -> Descendants<Ix> {
+        let mut descendants = self.descendants();
+        descendants.walk_next(arena);
+        descendants
+    }
+
+    /// Return an iterator of references to this node and its descendants, in post-order.
+    ///
+    /// Children appear before their parent, so the node itself comes last.
+    pub fn descendants_post_order(self) -> DescendantsPostOrder<Ix> {
+        DescendantsPostOrder(self.traverse())
+    }
+
+    /// Return an iterator of references to this node and its descendants, in reverse post-order.
+    ///
+    /// This yields the nodes of `descendants_post_order` back to front, so the node itself comes
+    /// first and children appear from the last to the first one.
+    pub fn reverse_descendants_post_order(self) -> ReverseDescendantsPostOrder<Ix> {
+        ReverseDescendantsPostOrder(self.reverse_traverse())
+    }
+
+    /// Return an iterator of references to this node and its descendants in level order, together
+    /// with their level below this node.
+    ///
+    /// The node itself comes first at level 0, followed by its children at level 1 and so on.
+    pub fn breadth_first(self) -> BreadthFirst<Ix> {
+        BreadthFirst {
+            queue: iter::once((self, 0)).collect(),
+        }
+    }
+
+    /// Return an iterator of references to this node and its descendants, in tree order.
+    pub fn traverse(self) -> Traverse<Ix> {
+        Traverse {
+            root: self,
+            next: Some(NodeEdge::Start(self)),
+            next_back: Some(NodeEdge::End(self)),
+            current: None,
+        }
+    }
+
+    /// Return an iterator of references to this node and its descendants in tree order, together
+    /// with their depth below this node.
+    ///
+    /// Both edges of this node are at depth 0, those of its children at depth 1 and so on.
+    pub fn traverse_with_depth(self) -> TraverseWithDepth<Ix> {
+        TraverseWithDepth {
+            traverse: self.traverse(),
+            depth: 0,
+        }
+    }
+
+    /// Return an iterator of references to this node and its descendants in tree order, together
+    /// with their depth below this node.
+    ///
+    /// This node itself comes first, at depth 0.
+    pub fn descendants_with_depth(self) -> DescendantsWithDepth<Ix> {
+        DescendantsWithDepth(self.traverse_with_depth())
+    }
+
+    /// Return an iterator of references to this node and its descendants, in tree order.
+    pub fn reverse_traverse(self) -> ReverseTraverse<Ix> {
+        ReverseTraverse(self.traverse())
+    }
+
+    /// Detach a node from its parent and siblings. Children are not affected.
+    ///
+    /// Panics if the node is not in the arena.
+    pub fn detach<T>(self, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_detach(arena) {
+            panic!("Can not detach node: {}", err)
+        }
+    }
+
+    /// Detach a node from its parent and siblings. Children are not affected.
+    pub fn try_detach<T>(self, arena: &mut Tree<T, Ix>) -> Result<(), NodeError> {
+        arena.check_node(self)?;
+        arena.preorder_layout = false;
+        let (parent, previous_sibling, next_sibling) = {
+            let node = &mut arena[self];
+            (
+                node.parent.take(),
+                node.previous_sibling.take(),
+                node.next_sibling.take(),
+            )
+        };
+
+        if let Some(next_sibling) = next_sibling {
+            arena[next_sibling].previous_sibling = previous_sibling;
+        } else if let Some(parent) = parent {
+            arena[parent].last_child = previous_sibling;
+        }
+
+        if let Some(previous_sibling) = previous_sibling {
+            arena[previous_sibling].next_sibling = next_sibling;
+        } else if let Some(parent) = parent {
+            arena[parent].first_child = next_sibling;
+        }
+        arena.roots.insert(self);
+        Ok(())
+    }
+
+    /// Check that `new_node` can be moved below or next to this node.
+    ///
+    /// This is not the case for this node itself and its ancestors, since they would become
+    /// their own descendants.
+    fn check_insertion<T>(
+        self,
+        new_node: NodeId<Ix>,
+        arena: &Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        arena.check_node(self)?;
+        arena.check_node(new_node)?;
+        if new_node == self {
+            Err(NodeError::SameNode)
+        } else if arena[new_node].first_child.is_none() {
+            // A leaf is nobody's ancestor, so trees built top-down never walk up the ancestors.
+            Ok(())
+        } else if self.ancestors().iter(arena).any(|ancestor| ancestor == new_node) {
+            Err(NodeError::WouldCreateCycle)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Append a new child to this node, after existing children.
+    ///
+    /// Panics if the new child is this node or one of its ancestors, or if either node is not in
+    /// the arena.
+    pub fn append<T>(self, new_child: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_append(new_child, arena) {
+            panic!("Can not append node: {}", err)
+        }
+    }
+
+    /// Append a new child to this node, after existing children.
+    ///
+    /// Fails without modifying the arena if the new child is this node or one of its ancestors, or
+    /// if either node is not in the arena.
+    pub fn try_append<T>(
+        self,
+        new_child: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_insertion(new_child, arena)?;
+        new_child.detach(arena);
+        self.link_last_child(new_child, arena);
+        Ok(())
+    }
+
+    /// Link a detached root to this node as its last child, without any checks.
+    fn link_last_child<T>(self, new_child: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        arena.preorder_layout = false;
+        let last_child_opt;
+        {
+            let (self_borrow, new_child_borrow) = arena.get_pair_mut(self, new_child);
+            debug_assert!(new_child_borrow.parent.is_none());
+            new_child_borrow.parent = Some(self);
+            last_child_opt = self_borrow.last_child.replace(new_child);
+            if let Some(last_child) = last_child_opt {
+                new_child_borrow.previous_sibling = Some(last_child);
+            } else {
+                debug_assert!(self_borrow.first_child.is_none());
+                self_borrow.first_child = Some(new_child);
+            }
+        }
+        if let Some(last_child) = last_child_opt {
+            debug_assert!(arena[last_child].next_sibling.is_none());
+            arena[last_child].next_sibling = Some(new_child);
+        }
+        arena.roots.remove(&new_child);
+    }
+
+    /// Prepend a new child to this node, before existing children.
+    ///
+    /// Panics if the new child is this node or one of its ancestors, or if either node is not in
+    /// the arena.
+    pub fn prepend<T>(self, new_child: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_prepend(new_child, arena) {
+            panic!("Can not prepend node: {}", err)
+        }
+    }
+
+    /// Prepend a new child to this node, before existing children.
+    ///
+    /// Fails without modifying the arena if the new child is this node or one of its ancestors, or
+    /// if either node is not in the arena.
+    pub fn try_prepend<T>(
+        self,
+        new_child: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_insertion(new_child, arena)?;
+        new_child.detach(arena);
+        let first_child_opt;
+        {
+            let (self_borrow, new_child_borrow) = arena.get_pair_mut(self, new_child);
+            new_child_borrow.parent = Some(self);
+            first_child_opt = self_borrow.first_child.replace(new_child);
+            if let Some(first_child) = first_child_opt {
+                new_child_borrow.next_sibling = Some(first_child);
+            } else {
+                debug_assert!(self_borrow.last_child.is_none());
+                self_borrow.last_child = Some(new_child);
+            }
+        }
+        if let Some(first_child) = first_child_opt {
+            debug_assert!(arena[first_child].previous_sibling.is_none());
+            arena[first_child].previous_sibling = Some(new_child);
+        }
+        arena.roots.remove(&new_child);
+        Ok(())
+    }
+
+    /// Copies and appends the root node of `from_tree` with its descendants to this node.
+    ///
+    /// See `Tree::root` for which node is considered the root. Returns the id of the copied root,
+    /// or `None` if `from_tree` is empty. Use `Tree::copy_subtree` to copy another node.
+    pub fn append_subtree<T: Clone>(
+        &mut self,
+        from_tree: &Tree<T, Ix>,
+        into_tree: &mut Tree<T, Ix>,
+    ) -> Option<NodeId<Ix>> {
+        let from_root_id = from_tree.root()?;
+        Some(into_tree.copy_subtree(from_tree, from_root_id, Some(*self)))
+    }
+
+    /// Detaches and marks the node and its children as reusable.
+    ///
+    /// The data of the nodes is dropped, use `Tree::remove_subtree` to get it back.
+    pub fn orphan<T>(self, arena: &mut Tree<T, Ix>) {
+        arena.remove_subtree(self);
+    }
+
+    /// Insert a new sibling after this node.
+    ///
+    /// Panics if the new sibling is this node or one of its ancestors, or if either node is not in
+    /// the arena.
+    pub fn insert_after<T>(self, new_sibling: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_insert_after(new_sibling, arena) {
+            panic!("Can not insert after node: {}", err)
+        }
+    }
+
+    /// Insert a new sibling after this node.
+    ///
+    /// Fails without modifying the arena if the new sibling is this node or one of its ancestors,
+    /// or if either node is not in the arena.
+    pub fn try_insert_after<T>(
+        self,
+        new_sibling: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_insertion(new_sibling, arena)?;
+        new_sibling.detach(arena);
+        let next_sibling_opt;
+        let parent_opt;
+        {
+            let (self_borrow, new_sibling_borrow) = arena.get_pair_mut(self, new_sibling);
             parent_opt = self_borrow.parent;
             new_sibling_borrow.parent = parent_opt;
             new_sibling_borrow.previous_sibling = Some(self);
-            next_sibling_opt = mem::replace(&mut self_borrow.next_sibling, Some(new_sibling));
+            next_sibling_opt = self_borrow.next_sibling.replace(new_sibling);
             if let Some(next_sibling) = next_sibling_opt {
                 new_sibling_borrow.next_sibling = Some(next_sibling);
             }
         }
-        if let Some(next_sibling) = next_sibling_opt {
-            debug_assert!(arena[next_sibling].previous_sibling.unwrap() == self);
-            arena[next_sibling].previous_sibling = Some(new_sibling);
-        } else if let Some(parent) = parent_opt {
-            debug_assert!(arena[parent].last_child.unwrap() == self);
-            arena[parent].last_child = Some(new_sibling);
+        if let Some(next_sibling) = next_sibling_opt {
+            debug_assert!(arena[next_sibling].previous_sibling.unwrap() == self);
+            arena[next_sibling].previous_sibling = Some(new_sibling);
+        } else if let Some(parent) = parent_opt {
+            debug_assert!(arena[parent].last_child.unwrap() == self);
+            arena[parent].last_child = Some(new_sibling);
+        }
+        arena.update_root(new_sibling);
+        Ok(())
+    }
+
+    /// Insert a new sibling before this node.
+    ///
+    /// Panics if the new sibling is this node or one of its ancestors, or if either node is not in
+    /// the arena.
+    pub fn insert_before<T>(self, new_sibling: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_insert_before(new_sibling, arena) {
+            panic!("Can not insert before node: {}", err)
+        }
+    }
+
+    /// Insert a new sibling before this node.
+    ///
+    /// Fails without modifying the arena if the new sibling is this node or one of its ancestors,
+    /// or if either node is not in the arena.
+    pub fn try_insert_before<T>(
+        self,
+        new_sibling: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_insertion(new_sibling, arena)?;
+        new_sibling.detach(arena);
+        let previous_sibling_opt;
+        let parent_opt;
+        {
+            let (self_borrow, new_sibling_borrow) = arena.get_pair_mut(self, new_sibling);
+            parent_opt = self_borrow.parent;
+            new_sibling_borrow.parent = parent_opt;
+            new_sibling_borrow.next_sibling = Some(self);
+            previous_sibling_opt = self_borrow.previous_sibling.replace(new_sibling);
+            if let Some(previous_sibling) = previous_sibling_opt {
+                new_sibling_borrow.previous_sibling = Some(previous_sibling);
+            }
+        }
+        if let Some(previous_sibling) = previous_sibling_opt {
+            debug_assert!(arena[previous_sibling].next_sibling.unwrap() == self);
+            arena[previous_sibling].next_sibling = Some(new_sibling);
+        } else if let Some(parent) = parent_opt {
+            debug_assert!(arena[parent].first_child.unwrap() == self);
+            arena[parent].first_child = Some(new_sibling);
+        }
+        arena.update_root(new_sibling);
+        Ok(())
+    }
+
+    /// Exchange the positions of this node and another one, together with their descendants.
+    ///
+    /// Panics if both are the same node, if one is an ancestor of the other, or if either node is
+    /// not in the arena.
+    pub fn swap_with<T>(self, other: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_swap_with(other, arena) {
+            panic!("Can not swap nodes: {}", err)
+        }
+    }
+
+    /// Exchange the positions of this node and another one, together with their descendants.
+    ///
+    /// Fails without modifying the arena if both are the same node, if one is an ancestor of the
+    /// other, or if either node is not in the arena.
+    pub fn try_swap_with<T>(
+        self,
+        other: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_insertion(other, arena)?;
+        other.check_insertion(self, arena)?;
+        if arena[self].next_sibling == Some(other) {
+            other.insert_after(self, arena);
+        } else if arena[other].next_sibling == Some(self) {
+            self.insert_after(other, arena);
+        } else {
+            // Neither node is next to the other, so their positions stay valid while both are
+            // detached.
+            let position = self.position(arena);
+            let other_position = other.position(arena);
+            self.detach(arena);
+            other.detach(arena);
+            other.place(position, arena);
+            self.place(other_position, arena);
+        }
+        Ok(())
+    }
+
+    /// Put another node and its descendants in the place of this node.
+    ///
+    /// This node keeps its descendants and becomes a root, use `orphan` to remove it.
+    ///
+    /// Panics if the new node is this node or one of its ancestors, or if either node is not in
+    /// the arena.
+    pub fn replace_with<T>(self, new_node: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_replace_with(new_node, arena) {
+            panic!("Can not replace node: {}", err)
+        }
+    }
+
+    /// Put another node and its descendants in the place of this node.
+    ///
+    /// Fails without modifying the arena if the new node is this node or one of its ancestors, or
+    /// if either node is not in the arena.
+    pub fn try_replace_with<T>(
+        self,
+        new_node: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_insertion(new_node, arena)?;
+        new_node.detach(arena);
+        let position = self.position(arena);
+        self.detach(arena);
+        new_node.place(position, arena);
+        Ok(())
+    }
+
+    /// Move the siblings from `first` to `last` after the existing children of this node, together
+    /// with their descendants.
+    ///
+    /// Panics if this node is in the range or below it, if `last` does not follow `first` among its
+    /// siblings, or if any node is not in the arena.
+    pub fn append_range<T>(self, first: NodeId<Ix>, last: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_append_range(first, last, arena) {
+            panic!("Can not append nodes: {}", err)
+        }
+    }
+
+    /// Move the siblings from `first` to `last` after the existing children of this node, together
+    /// with their descendants.
+    ///
+    /// The range is spliced with a constant number of sibling link updates, only the parent of
+    /// each moved node is updated one by one, and not at all when it stays the same.
+    ///
+    /// Fails without modifying the arena if this node is in the range or below it, if `last` does
+    /// not follow `first` among its siblings, or if any node is not in the arena.
+    pub fn try_append_range<T>(
+        self,
+        first: NodeId<Ix>,
+        last: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_range_insertion(first, last, arena)?;
+        let old_parent = Self::unlink_range(first, last, arena);
+        let previous_sibling = arena[self].last_child;
+        Self::link_range(first, last, old_parent, Some(self), previous_sibling, None, arena);
+        Ok(())
+    }
+
+    /// Move the siblings from `first` to `last` after this node, together with their
+    /// descendants.
+    ///
+    /// Panics if this node is in the range or below it, if `last` does not follow `first` among its
+    /// siblings, or if any node is not in the arena.
+    pub fn insert_range_after<T>(
+        self,
+        first: NodeId<Ix>,
+        last: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) {
+        if let Err(err) = self.try_insert_range_after(first, last, arena) {
+            panic!("Can not insert nodes after node: {}", err)
+        }
+    }
+
+    /// Move the siblings from `first` to `last` after this node, together with their
+    /// descendants.
+    ///
+    /// The range is spliced like with `try_append_range`.
+    ///
+    /// Fails without modifying the arena if this node is in the range or below it, if `last` does
+    /// not follow `first` among its siblings, or if any node is not in the arena.
+    pub fn try_insert_range_after<T>(
+        self,
+        first: NodeId<Ix>,
+        last: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        self.check_range_insertion(first, last, arena)?;
+        let old_parent = Self::unlink_range(first, last, arena);
+        let (parent, next_sibling) = (arena[self].parent, arena[self].next_sibling);
+        Self::link_range(first, last, old_parent, parent, Some(self), next_sibling, arena);
+        Ok(())
+    }
+
+    /// Move all children of this node after the existing children of another node, together with
+    /// their descendants.
+    ///
+    /// Panics if the new parent is a descendant of this node, or if either node is not in the
+    /// arena.
+    pub fn move_children_to<T>(self, new_parent: NodeId<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Err(err) = self.try_move_children_to(new_parent, arena) {
+            panic!("Can not move children: {}", err)
+        }
+    }
+
+    /// Move all children of this node after the existing children of another node, together with
+    /// their descendants.
+    ///
+    /// The children are spliced like with `try_append_range`.
+    ///
+    /// Fails without modifying the arena if the new parent is a descendant of this node, or if
+    /// either node is not in the arena.
+    pub fn try_move_children_to<T>(
+        self,
+        new_parent: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        arena.check_node(self)?;
+        arena.check_node(new_parent)?;
+        if new_parent.strict_ancestors(arena).iter(arena).any(|ancestor| ancestor == self) {
+            return Err(NodeError::WouldCreateCycle);
+        }
+        match (arena[self].first_child, arena[self].last_child) {
+            (Some(first_child), Some(last_child)) => {
+                new_parent.try_append_range(first_child, last_child, arena)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Sort the children of this node with a comparator function on their data.
+    ///
+    /// The children are relinked in place. This sort is unstable, so children comparing equal may
+    /// be reordered, use `stable_sort_children_by` to keep their order.
+    pub fn sort_children_by<T, F>(self, arena: &mut Tree<T, Ix>, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> cmp::Ordering,
+    {
+        self.reorder_children(&mut Vec::new(), arena, |children, arena| {
+            children.sort_unstable_by(|&a, &b| compare(&arena[a].data, &arena[b].data))
+        });
+    }
+
+    /// Sort the children of this node with a key extraction function on their data.
+    ///
+    /// The children are relinked in place. This sort is unstable, so children with equal keys may
+    /// be reordered, use `stable_sort_children_by_key` to keep their order.
+    pub fn sort_children_by_key<T, K, F>(self, arena: &mut Tree<T, Ix>, mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        self.reorder_children(&mut Vec::new(), arena, |children, arena| {
+            children.sort_unstable_by_key(|&id| f(&arena[id].data))
+        });
+    }
+
+    /// Sort the children of this node with a comparator function on their data, keeping the order
+    /// of children comparing equal.
+    ///
+    /// The children are relinked in place.
+    pub fn stable_sort_children_by<T, F>(self, arena: &mut Tree<T, Ix>, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> cmp::Ordering,
+    {
+        self.reorder_children(&mut Vec::new(), arena, |children, arena| {
+            children.sort_by(|&a, &b| compare(&arena[a].data, &arena[b].data))
+        });
+    }
+
+    /// Sort the children of this node with a key extraction function on their data, keeping the
+    /// order of children with equal keys.
+    ///
+    /// The children are relinked in place.
+    pub fn stable_sort_children_by_key<T, K, F>(self, arena: &mut Tree<T, Ix>, mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        self.reorder_children(&mut Vec::new(), arena, |children, arena| {
+            children.sort_by_key(|&id| f(&arena[id].data))
+        });
+    }
+
+    /// Sort the children of this node and of all its descendants with a comparator function on
+    /// their data, like `sort_children_by`.
+    ///
+    /// The nodes are relinked in place while walking the subtree, without recursion.
+    pub fn sort_descendants_by<T, F>(self, arena: &mut Tree<T, Ix>, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> cmp::Ordering,
+    {
+        let mut children = Vec::new();
+        let mut node = self;
+        loop {
+            node.reorder_children(&mut children, arena, |children, arena| {
+                children.sort_unstable_by(|&a, &b| compare(&arena[a].data, &arena[b].data))
+            });
+            if let Some(first_child) = arena[node].first_child {
+                node = first_child;
+                continue;
+            }
+            // Climb up to the next node whose children are not sorted yet.
+            loop {
+                if node == self {
+                    return;
+                }
+                if let Some(next_sibling) = arena[node].next_sibling {
+                    node = next_sibling;
+                    break;
+                }
+                node = arena[node].parent.expect("descendant without a parent");
+            }
+        }
+    }
+
+    /// Return the position of this node within its parent and siblings.
+    fn position<T>(self, arena: &Tree<T, Ix>) -> Position<Ix> {
+        let node = &arena[self];
+        Position {
+            parent: node.parent,
+            previous_sibling: node.previous_sibling,
+            next_sibling: node.next_sibling,
         }
     }
 
-    /// Insert a new sibling before this node.
-    pub fn insert_before<T>(self, new_sibling: NodeId, arena: &mut Tree<T>) {
-        new_sibling.detach(arena);
-        let previous_sibling_opt;
-        let parent_opt;
-        {
-            let (self_borrow, new_sibling_borrow) =
-                arena
-                    .nodes
-                    .get_pair_mut(self.index, new_sibling.index, "Can not insert a node before itself");
-            parent_opt = self_borrow.parent;
-            new_sibling_borrow.parent = parent_opt;
-            new_sibling_borrow.next_sibling = Some(self);
-            previous_sibling_opt = mem::replace(&mut self_borrow.previous_sibling, Some(new_sibling));
-            if let Some(previous_sibling) = previous_sibling_opt {
-                new_sibling_borrow.previous_sibling = Some(previous_sibling);
+    /// Move this detached node to a position returned by `position`.
+    fn place<T>(self, position: Position<Ix>, arena: &mut Tree<T, Ix>) {
+        if let Some(previous_sibling) = position.previous_sibling {
+            previous_sibling.insert_after(self, arena);
+        } else if let Some(next_sibling) = position.next_sibling {
+            next_sibling.insert_before(self, arena);
+        } else if let Some(parent) = position.parent {
+            parent.append(self, arena);
+        }
+    }
+
+    /// Check that the siblings from `first` to `last` can be moved below or next to this node.
+    ///
+    /// This is not the case when this node is in the range or below it, or when the nodes are not
+    /// a range of siblings.
+    fn check_range_insertion<T>(
+        self,
+        first: NodeId<Ix>,
+        last: NodeId<Ix>,
+        arena: &Tree<T, Ix>,
+    ) -> Result<(), NodeError> {
+        arena.check_node(self)?;
+        arena.check_node(first)?;
+        arena.check_node(last)?;
+        // The only ancestor of this node which can be in the range is a sibling of the range.
+        let parent = arena[first].parent;
+        let sibling = self
+            .ancestors()
+            .iter(arena)
+            .find(|&ancestor| arena[ancestor].parent == parent);
+        let mut node = first;
+        loop {
+            if Some(node) == sibling {
+                return Err(if node == self {
+                    NodeError::SameNode
+                } else {
+                    NodeError::WouldCreateCycle
+                });
+            }
+            if node == last {
+                return Ok(());
             }
+            node = arena[node].next_sibling.ok_or(NodeError::NotSiblings)?;
         }
-        if let Some(previous_sibling) = previous_sibling_opt {
-            debug_assert!(arena[previous_sibling].next_sibling.unwrap() == self);
-            arena[previous_sibling].next_sibling = Some(new_sibling);
-        } else if let Some(parent) = parent_opt {
-            debug_assert!(arena[parent].first_child.unwrap() == self);
-            arena[parent].first_child = Some(new_sibling);
+    }
+
+    /// Unlink the siblings from `first` to `last` from their parent and the siblings around them,
+    /// returning the parent.
+    ///
+    /// The nodes in the range keep pointing to their old parent.
+    fn unlink_range<T>(
+        first: NodeId<Ix>,
+        last: NodeId<Ix>,
+        arena: &mut Tree<T, Ix>,
+    ) -> Option<NodeId<Ix>> {
+        arena.preorder_layout = false;
+        let parent = arena[first].parent;
+        let previous_sibling = arena[first].previous_sibling.take();
+        let next_sibling = arena[last].next_sibling.take();
+        if let Some(next_sibling) = next_sibling {
+            arena[next_sibling].previous_sibling = previous_sibling;
+        } else if let Some(parent) = parent {
+            arena[parent].last_child = previous_sibling;
+        }
+        if let Some(previous_sibling) = previous_sibling {
+            arena[previous_sibling].next_sibling = next_sibling;
+        } else if let Some(parent) = parent {
+            arena[parent].first_child = next_sibling;
+        }
+        parent
+    }
+
+    /// Link the siblings from `first` to `last`, unlinked from `old_parent`, below `parent` and
+    /// between the given siblings.
+    fn link_range<T>(
+        first: NodeId<Ix>,
+        last: NodeId<Ix>,
+        old_parent: Option<NodeId<Ix>>,
+        parent: Option<NodeId<Ix>>,
+        previous_sibling: Option<NodeId<Ix>>,
+        next_sibling: Option<NodeId<Ix>>,
+        arena: &mut Tree<T, Ix>,
+    ) {
+        arena[first].previous_sibling = previous_sibling;
+        arena[last].next_sibling = next_sibling;
+        if let Some(previous_sibling) = previous_sibling {
+            arena[previous_sibling].next_sibling = Some(first);
+        } else if let Some(parent) = parent {
+            arena[parent].first_child = Some(first);
+        }
+        if let Some(next_sibling) = next_sibling {
+            arena[next_sibling].previous_sibling = Some(last);
+        } else if let Some(parent) = parent {
+            arena[parent].last_child = Some(last);
+        }
+        if parent == old_parent {
+            return;
+        }
+        let mut node = first;
+        loop {
+            arena[node].parent = parent;
+            arena.update_root(node);
+            if node == last {
+                break;
+            }
+            node = arena[node].next_sibling.expect("range of siblings without its last node");
+        }
+    }
+
+    /// Reorder the children of this node by sorting their ids, using `children` as a buffer.
+    fn reorder_children<T, F>(
+        self,
+        children: &mut Vec<NodeId<Ix>>,
+        arena: &mut Tree<T, Ix>,
+        sort: F,
+    ) where
+        F: FnOnce(&mut [NodeId<Ix>], &Tree<T, Ix>),
+    {
+        children.clear();
+        children.extend(self.children(arena).iter(arena));
+        if children.len() < 2 {
+            return;
+        }
+        sort(children, arena);
+        arena.preorder_layout = false;
+        let mut previous_sibling = None;
+        for &child in children.iter() {
+            if let Some(previous_sibling) = previous_sibling {
+                arena[previous_sibling].next_sibling = Some(child);
+            }
+            arena[child].previous_sibling = previous_sibling;
+            previous_sibling = Some(child);
         }
+        let (first_child, last_child) = (children[0], children[children.len() - 1]);
+        arena[first_child].previous_sibling = None;
+        arena[last_child].next_sibling = None;
+        arena[self].first_child = Some(first_child);
+        arena[self].last_child = Some(last_child);
     }
 }
 
+/// The place of a node within its parent and siblings
+struct Position<Ix: IndexType> {
+    parent: Option<NodeId<Ix>>,
+    previous_sibling: Option<NodeId<Ix>>,
+    next_sibling: Option<NodeId<Ix>>,
+}
+
 macro_rules! impl_node_walker {
     ($name:ident, $next:expr) => {
-        impl<T> Walker<T> for $name {
-            type Item = NodeId;
+        impl<T, Ix: IndexType> Walker<T, Ix> for $name<Ix> {
+            type Item = NodeId<Ix>;
 
-            fn walk_next(&mut self, arena: &Tree<T>) -> Option<NodeId> {
+            fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
                 match self.node.take() {
                     Some(node) => {
                         self.node = $next(&arena[node]);
@@ -472,46 +1944,223 @@ macro_rules! impl_node_walker {
                 }
             }
         }
+
+        impl<T, Ix: IndexType> FusedWalker<T, Ix> for $name<Ix> {}
     };
 }
 
 /// An iterator of references to the ancestors a given node.
-pub struct Ancestors {
-    node: Option<NodeId>,
+pub struct Ancestors<Ix: IndexType = DefaultIx> {
+    node: Option<NodeId<Ix>>,
 }
-impl_node_walker!(Ancestors, |node: &Node<T>| node.parent);
+impl_node_walker!(Ancestors, |node: &Node<T, Ix>| node.parent);
 
 /// An iterator of references to the siblings before a given node.
-pub struct PrecedingSiblings {
-    node: Option<NodeId>,
+pub struct PrecedingSiblings<Ix: IndexType = DefaultIx> {
+    node: Option<NodeId<Ix>>,
 }
-impl_node_walker!(PrecedingSiblings, |node: &Node<T>| node.previous_sibling);
+impl_node_walker!(PrecedingSiblings, |node: &Node<T, Ix>| node.previous_sibling);
 
 /// An iterator of references to the siblings after a given node.
-pub struct FollowingSiblings {
-    node: Option<NodeId>,
+pub struct FollowingSiblings<Ix: IndexType = DefaultIx> {
+    node: Option<NodeId<Ix>>,
+}
+impl_node_walker!(FollowingSiblings, |node: &Node<T, Ix>| node.next_sibling);
+
+/// The siblings between two nodes, which can be taken from both ends
+struct SiblingRange<Ix: IndexType> {
+    front: Option<NodeId<Ix>>,
+    back: Option<NodeId<Ix>>,
+    // The nodes yielded last from each end, where the other end has to stop.
+    last_front: Option<NodeId<Ix>>,
+    last_back: Option<NodeId<Ix>>,
+}
+
+impl<Ix: IndexType> SiblingRange<Ix> {
+    fn children<T>(parent: NodeId<Ix>, arena: &Tree<T, Ix>) -> Self {
+        SiblingRange {
+            front: arena[parent].first_child,
+            back: arena[parent].last_child,
+            last_front: None,
+            last_back: None,
+        }
+    }
+
+    fn next_front<T>(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        let node = self.front.take()?;
+        if Some(node) == self.last_back {
+            self.back = None;
+            return None;
+        }
+        self.front = arena[node].next_sibling;
+        self.last_front = Some(node);
+        Some(node)
+    }
+
+    fn next_back<T>(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        let node = self.back.take()?;
+        if Some(node) == self.last_front {
+            self.front = None;
+            return None;
+        }
+        self.back = arena[node].previous_sibling;
+        self.last_back = Some(node);
+        Some(node)
+    }
 }
-impl_node_walker!(FollowingSiblings, |node: &Node<T>| node.next_sibling);
 
 /// An iterator of references to the children of a given node.
-pub struct Children {
-    node: Option<NodeId>,
+pub struct Children<Ix: IndexType = DefaultIx>(SiblingRange<Ix>);
+
+impl<T, Ix: IndexType> Walker<T, Ix> for Children<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        self.0.next_front(arena)
+    }
+}
+
+impl<T, Ix: IndexType> DoubleEndedWalker<T, Ix> for Children<Ix> {
+    fn walk_next_back(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        self.0.next_back(arena)
+    }
 }
-impl_node_walker!(Children, |node: &Node<T>| node.next_sibling);
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for Children<Ix> {}
 
 /// An iterator of references to the children of a given node, in reverse order.
-pub struct ReverseChildren {
-    node: Option<NodeId>,
+pub struct ReverseChildren<Ix: IndexType = DefaultIx>(SiblingRange<Ix>);
+
+impl<T, Ix: IndexType> Walker<T, Ix> for ReverseChildren<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        self.0.next_back(arena)
+    }
+}
+
+impl<T, Ix: IndexType> DoubleEndedWalker<T, Ix> for ReverseChildren<Ix> {
+    fn walk_next_back(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        self.0.next_front(arena)
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for ReverseChildren<Ix> {}
+
+/// An iterator of references to the siblings of a given node, in tree order.
+pub struct Siblings<Ix: IndexType = DefaultIx> {
+    node: Option<NodeId<Ix>>,
+    skip: NodeId<Ix>,
+}
+
+impl<T, Ix: IndexType> Walker<T, Ix> for Siblings<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        let mut node = self.node?;
+        if node == self.skip {
+            node = arena[node].next_sibling?;
+        }
+        self.node = arena[node].next_sibling;
+        Some(node)
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for Siblings<Ix> {}
+
+/// An iterator of references to the nodes after a given node in tree order, without its
+/// descendants.
+pub struct Following<Ix: IndexType = DefaultIx> {
+    node: Option<NodeId<Ix>>,
+}
+
+impl<Ix: IndexType> Following<Ix> {
+    /// Return the first node after the subtree of the given node in tree order.
+    fn skip_subtree<T>(mut node: NodeId<Ix>, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        loop {
+            if let Some(next_sibling) = arena[node].next_sibling {
+                return Some(next_sibling);
+            }
+            node = arena[node].parent?;
+        }
+    }
+}
+
+impl<T, Ix: IndexType> Walker<T, Ix> for Following<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        let node = self.node?;
+        self.node = arena[node].first_child.or_else(|| Following::skip_subtree(node, arena));
+        Some(node)
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for Following<Ix> {}
+
+/// An iterator of references to the nodes before a given node in reverse tree order, without its
+/// ancestors.
+pub struct Preceding<Ix: IndexType = DefaultIx> {
+    node: Option<NodeId<Ix>>,
+    // The lowest ancestor of the given node, or the node itself, which has not been passed yet.
+    ancestor: NodeId<Ix>,
+}
+
+impl<Ix: IndexType> Preceding<Ix> {
+    /// Return the node before the given one in tree order, skipping the ancestors of `ancestor`.
+    fn previous<T>(
+        mut node: NodeId<Ix>,
+        ancestor: &mut NodeId<Ix>,
+        arena: &Tree<T, Ix>,
+    ) -> Option<NodeId<Ix>> {
+        loop {
+            if let Some(mut previous) = arena[node].previous_sibling {
+                while let Some(last_child) = arena[previous].last_child {
+                    previous = last_child;
+                }
+                return Some(previous);
+            }
+            let parent = arena[node].parent?;
+            if Some(parent) != arena[*ancestor].parent {
+                return Some(parent);
+            }
+            *ancestor = parent;
+            node = parent;
+        }
+    }
+}
+
+impl<T, Ix: IndexType> Walker<T, Ix> for Preceding<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        let node = self.node?;
+        self.node = Preceding::previous(node, &mut self.ancestor, arena);
+        Some(node)
+    }
 }
-impl_node_walker!(ReverseChildren, |node: &Node<T>| node.previous_sibling);
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for Preceding<Ix> {}
 
 /// An iterator of references to a given node and its descendants, in tree order.
-pub struct Descendants(Traverse);
+pub struct Descendants<Ix: IndexType = DefaultIx>(Traverse<Ix>);
+
+impl<Ix: IndexType> Descendants<Ix> {
+    /// Skip the descendants of the node yielded last.
+    pub fn skip_subtree(&mut self) {
+        self.0.skip_subtree();
+    }
+
+    /// End the iteration, without yielding any further nodes.
+    pub fn stop(&mut self) {
+        self.0.stop();
+    }
+}
 
-impl<T> Walker<T> for Descendants {
-    type Item = NodeId;
+impl<T, Ix: IndexType> Walker<T, Ix> for Descendants<Ix> {
+    type Item = NodeId<Ix>;
 
-    fn walk_next(&mut self, arena: &Tree<T>) -> Option<NodeId> {
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
         loop {
             match self.0.walk_next(arena) {
                 Some(NodeEdge::Start(node)) => return Some(node),
@@ -522,7 +2171,105 @@ impl<T> Walker<T> for Descendants {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<T, Ix: IndexType> DoubleEndedWalker<T, Ix> for Descendants<Ix> {
+    fn walk_next_back(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        loop {
+            match self.0.walk_next_back(arena) {
+                Some(NodeEdge::Start(node)) => return Some(node),
+                Some(NodeEdge::End(_)) => {}
+                None => return None,
+            }
+        }
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for Descendants<Ix> {}
+
+/// An iterator of references to a given node and its descendants, in post-order.
+pub struct DescendantsPostOrder<Ix: IndexType = DefaultIx>(Traverse<Ix>);
+
+impl<T, Ix: IndexType> Walker<T, Ix> for DescendantsPostOrder<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        loop {
+            match self.0.walk_next(arena) {
+                Some(NodeEdge::Start(_)) => {}
+                Some(NodeEdge::End(node)) => return Some(node),
+                None => return None,
+            }
+        }
+    }
+}
+
+impl<T, Ix: IndexType> DoubleEndedWalker<T, Ix> for DescendantsPostOrder<Ix> {
+    fn walk_next_back(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        loop {
+            match self.0.walk_next_back(arena) {
+                Some(NodeEdge::End(node)) => return Some(node),
+                Some(NodeEdge::Start(_)) => {}
+                None => return None,
+            }
+        }
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for DescendantsPostOrder<Ix> {}
+
+/// An iterator of references to a given node and its descendants, in reverse post-order.
+pub struct ReverseDescendantsPostOrder<Ix: IndexType = DefaultIx>(ReverseTraverse<Ix>);
+
+impl<T, Ix: IndexType> Walker<T, Ix> for ReverseDescendantsPostOrder<Ix> {
+    type Item = NodeId<Ix>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        loop {
+            match self.0.walk_next(arena) {
+                Some(NodeEdge::Start(_)) => {}
+                Some(NodeEdge::End(node)) => return Some(node),
+                None => return None,
+            }
+        }
+    }
+}
+
+impl<T, Ix: IndexType> DoubleEndedWalker<T, Ix> for ReverseDescendantsPostOrder<Ix> {
+    fn walk_next_back(&mut self, arena: &Tree<T, Ix>) -> Option<NodeId<Ix>> {
+        loop {
+            match self.0.walk_next_back(arena) {
+                Some(NodeEdge::End(node)) => return Some(node),
+                Some(NodeEdge::Start(_)) => {}
+                None => return None,
+            }
+        }
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for ReverseDescendantsPostOrder<Ix> {}
+
+/// An iterator of references to a given node and its descendants in level order, together with
+/// their level below the given node.
+pub struct BreadthFirst<Ix: IndexType = DefaultIx> {
+    queue: VecDeque<(NodeId<Ix>, usize)>,
+}
+
+impl<T, Ix: IndexType> Walker<T, Ix> for BreadthFirst<Ix> {
+    type Item = (NodeId<Ix>, usize);
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<(NodeId<Ix>, usize)> {
+        let (node, level) = self.queue.pop_front()?;
+        let mut child = arena[node].first_child;
+        while let Some(id) = child {
+            self.queue.push_back((id, level + 1));
+            child = arena[id].next_sibling;
+        }
+        Some((node, level))
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for BreadthFirst<Ix> {}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 /// Indicator if the node is at a start or endpoint of the tree
 pub enum NodeEdge<T> {
     /// Indicates that start of a node that has children. Yielded by `Traverse::next` before the
@@ -535,91 +2282,222 @@ pub enum NodeEdge<T> {
 }
 
 /// An iterator of references to a given node and its descendants, in tree order.
-pub struct Traverse {
-    root: NodeId,
-    next: Option<NodeEdge<NodeId>>,
-}
-
-impl<T> Walker<T> for Traverse {
-    type Item = NodeEdge<NodeId>;
-
-    fn walk_next(&mut self, arena: &Tree<T>) -> Option<NodeEdge<NodeId>> {
-        match self.next.take() {
-            Some(item) => {
-                self.next = match item {
-                    NodeEdge::Start(node) => match arena[node].first_child {
-                        Some(first_child) => Some(NodeEdge::Start(first_child)),
-                        None => Some(NodeEdge::End(node)),
-                    },
-                    NodeEdge::End(node) => {
-                        if node == self.root {
-                            None
-                        } else {
-                            match arena[node].next_sibling {
-                                Some(next_sibling) => Some(NodeEdge::Start(next_sibling)),
-                                None => {
-                                    match arena[node].parent {
-                                        Some(parent) => Some(NodeEdge::End(parent)),
-
-                                        // `node.parent()` here can only be `None`
-                                        // if the tree has been modified during iteration,
-                                        // but silently stoping iteration
-                                        // seems a more sensible behavior than panicking.
-                                        None => None,
-                                    }
-                                }
-                            }
+pub struct Traverse<Ix: IndexType = DefaultIx> {
+    root: NodeId<Ix>,
+    next: Option<NodeEdge<NodeId<Ix>>>,
+    next_back: Option<NodeEdge<NodeId<Ix>>>,
+    // The node whose `Start` was yielded last, unless an `End` followed.
+    current: Option<NodeId<Ix>>,
+}
+
+impl<Ix: IndexType> Traverse<Ix> {
+    /// Skip the descendants of the node whose `Start` was yielded last.
+    ///
+    /// The `End` of that node is still yielded next. Nothing is skipped if the last item was an
+    /// `End`. This must not be mixed with taking items from the back.
+    pub fn skip_subtree(&mut self) {
+        if let Some(node) = self.current.take() {
+            self.next = Some(NodeEdge::End(node));
+        }
+    }
+
+    /// End the traversal, without yielding any further items.
+    pub fn stop(&mut self) {
+        self.next = None;
+        self.next_back = None;
+        self.current = None;
+    }
+
+    /// Return the edge following the given one, in tree order.
+    fn next_edge<T>(
+        &self,
+        edge: NodeEdge<NodeId<Ix>>,
+        arena: &Tree<T, Ix>,
+    ) -> Option<NodeEdge<NodeId<Ix>>> {
+        match edge {
+            NodeEdge::Start(node) => match arena[node].first_child {
+                Some(first_child) => Some(NodeEdge::Start(first_child)),
+                None => Some(NodeEdge::End(node)),
+            },
+            NodeEdge::End(node) => {
+                if node == self.root {
+                    None
+                } else {
+                    match arena[node].next_sibling {
+                        Some(next_sibling) => Some(NodeEdge::Start(next_sibling)),
+                        None => {
+                            // `node.parent()` here can only be `None`
+                            // if the tree has been modified during iteration,
+                            // but silently stoping iteration
+                            // seems a more sensible behavior than panicking.
+                            arena[node].parent.map(NodeEdge::End)
                         }
                     }
-                };
-                Some(item)
+                }
             }
-            None => None,
         }
     }
-}
 
-/// An iterator of references to a given node and its descendants, in reverse tree order.
-pub struct ReverseTraverse {
-    root: NodeId,
-    next: Option<NodeEdge<NodeId>>,
-}
-
-impl<T> Walker<T> for ReverseTraverse {
-    type Item = NodeEdge<NodeId>;
-
-    fn walk_next(&mut self, arena: &Tree<T>) -> Option<NodeEdge<NodeId>> {
-        match self.next.take() {
-            Some(item) => {
-                self.next = match item {
-                    NodeEdge::End(node) => match arena[node].last_child {
-                        Some(last_child) => Some(NodeEdge::End(last_child)),
-                        None => Some(NodeEdge::Start(node)),
-                    },
-                    NodeEdge::Start(node) => {
-                        if node == self.root {
-                            None
-                        } else {
-                            match arena[node].previous_sibling {
-                                Some(previous_sibling) => Some(NodeEdge::End(previous_sibling)),
-                                None => {
-                                    match arena[node].parent {
-                                        Some(parent) => Some(NodeEdge::Start(parent)),
-
-                                        // `node.parent()` here can only be `None`
-                                        // if the tree has been modified during iteration,
-                                        // but silently stoping iteration
-                                        // seems a more sensible behavior than panicking.
-                                        None => None,
-                                    }
-                                }
-                            }
+    /// Return the edge preceding the given one, in tree order.
+    fn previous_edge<T>(
+        &self,
+        edge: NodeEdge<NodeId<Ix>>,
+        arena: &Tree<T, Ix>,
+    ) -> Option<NodeEdge<NodeId<Ix>>> {
+        match edge {
+            NodeEdge::End(node) => match arena[node].last_child {
+                Some(last_child) => Some(NodeEdge::End(last_child)),
+                None => Some(NodeEdge::Start(node)),
+            },
+            NodeEdge::Start(node) => {
+                if node == self.root {
+                    None
+                } else {
+                    match arena[node].previous_sibling {
+                        Some(previous_sibling) => Some(NodeEdge::End(previous_sibling)),
+                        None => {
+                            // `node.parent()` here can only be `None`
+                            // if the tree has been modified during iteration,
+                            // but silently stoping iteration
+                            // seems a more sensible behavior than panicking.
+                            arena[node].parent.map(NodeEdge::Start)
                         }
                     }
-                };
-                Some(item)
+                }
+            }
+        }
+    }
+}
+
+impl<T, Ix: IndexType> Walker<T, Ix> for Traverse<Ix> {
+    type Item = NodeEdge<NodeId<Ix>>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeEdge<NodeId<Ix>>> {
+        let item = self.next.take()?;
+        self.current = match item {
+            NodeEdge::Start(node) => Some(node),
+            NodeEdge::End(_) => None,
+        };
+        if Some(item) == self.next_back {
+            self.next_back = None;
+        } else {
+            self.next = self.next_edge(item, arena);
+        }
+        Some(item)
+    }
+}
+
+impl<T, Ix: IndexType> DoubleEndedWalker<T, Ix> for Traverse<Ix> {
+    fn walk_next_back(&mut self, arena: &Tree<T, Ix>) -> Option<NodeEdge<NodeId<Ix>>> {
+        let item = self.next_back.take()?;
+        if Some(item) == self.next {
+            self.next = None;
+        } else {
+            self.next_back = self.previous_edge(item, arena);
+        }
+        Some(item)
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for Traverse<Ix> {}
+
+/// An iterator of references to a given node and its descendants in tree order, together with
+/// their depth below the given node.
+pub struct TraverseWithDepth<Ix: IndexType = DefaultIx> {
+    traverse: Traverse<Ix>,
+    depth: usize,
+}
+
+impl<T, Ix: IndexType> Walker<T, Ix> for TraverseWithDepth<Ix> {
+    type Item = (NodeEdge<NodeId<Ix>>, usize);
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<(NodeEdge<NodeId<Ix>>, usize)> {
+        let edge = self.traverse.walk_next(arena)?;
+        let depth = match edge {
+            NodeEdge::Start(_) => {
+                self.depth += 1;
+                self.depth - 1
+            }
+            NodeEdge::End(_) => {
+                // The depth can only run out if the tree has been modified during iteration and
+                // the walk left the subtree, so stop like `Traverse` does.
+                if self.depth == 0 {
+                    self.traverse.stop();
+                    return None;
+                }
+                self.depth -= 1;
+                self.depth
+            }
+        };
+        Some((edge, depth))
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for TraverseWithDepth<Ix> {}
+
+/// An iterator of references to a given node and its descendants in tree order, together with
+/// their depth below the given node.
+pub struct DescendantsWithDepth<Ix: IndexType = DefaultIx>(TraverseWithDepth<Ix>);
+
+impl<T, Ix: IndexType> Walker<T, Ix> for DescendantsWithDepth<Ix> {
+    type Item = (NodeId<Ix>, usize);
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<(NodeId<Ix>, usize)> {
+        loop {
+            match self.0.walk_next(arena) {
+                Some((NodeEdge::Start(node), depth)) => return Some((node, depth)),
+                Some((NodeEdge::End(_), _)) => {}
+                None => return None,
+            }
+        }
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for DescendantsWithDepth<Ix> {}
+
+/// An iterator of references to a given node and its descendants, in reverse tree order.
+pub struct ReverseTraverse<Ix: IndexType = DefaultIx>(Traverse<Ix>);
+
+impl<T, Ix: IndexType> Walker<T, Ix> for ReverseTraverse<Ix> {
+    type Item = NodeEdge<NodeId<Ix>>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeEdge<NodeId<Ix>>> {
+        self.0.walk_next_back(arena)
+    }
+}
+
+impl<T, Ix: IndexType> DoubleEndedWalker<T, Ix> for ReverseTraverse<Ix> {
+    fn walk_next_back(&mut self, arena: &Tree<T, Ix>) -> Option<NodeEdge<NodeId<Ix>>> {
+        self.0.walk_next(arena)
+    }
+}
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for ReverseTraverse<Ix> {}
+
+/// An iterator of references to all nodes of a `Tree`, in tree order.
+pub struct ForestTraverse<Ix: IndexType = DefaultIx> {
+    root: Option<NodeId<Ix>>,
+    traverse: Option<Traverse<Ix>>,
+}
+
+impl<T, Ix: IndexType> Walker<T, Ix> for ForestTraverse<Ix> {
+    type Item = NodeEdge<NodeId<Ix>>;
+
+    fn walk_next(&mut self, arena: &Tree<T, Ix>) -> Option<NodeEdge<NodeId<Ix>>> {
+        loop {
+            if let Some(edge) = self.traverse.as_mut()?.walk_next(arena) {
+                return Some(edge);
             }
-            None => None,
+            self.root = self.root.and_then(|root| {
+                arena
+                    .roots
+                    .range((Bound::Excluded(root), Bound::Unbounded))
+                    .next()
+                    .cloned()
+            });
+            self.traverse = self.root.map(NodeId::traverse);
         }
     }
 }
+
+impl<T, Ix: IndexType> FusedWalker<T, Ix> for ForestTraverse<Ix> {}
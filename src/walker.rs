@@ -1,4 +1,5 @@
-use Tree;
+use std::iter::FusedIterator;
+use {DefaultIx, IndexType, NodeId, Tree};
 
 // Taken and adapted from https://github.com/bluss/petgraph
 /// A walker is a traversal state, but where part of the traversal
@@ -6,13 +7,13 @@ use Tree;
 ///
 /// This for example allows graph traversals that don't hold a borrow of the
 /// graph they are traversing.
-pub trait Walker<T> {
+pub trait Walker<T, Ix: IndexType = DefaultIx> {
     type Item;
     /// Advance to the next item
-    fn walk_next(&mut self, context: &Tree<T>) -> Option<Self::Item>;
+    fn walk_next(&mut self, context: &Tree<T, Ix>) -> Option<Self::Item>;
 
     /// Create an iterator out of the walker and given `context`.
-    fn iter<'a>(self, arena: &'a Tree<T>) -> WalkerIter<'a, Self, T>
+    fn iter<'a>(self, arena: &'a Tree<T, Ix>) -> WalkerIter<'a, Self, T, Ix>
         where Self: Sized
     {
         WalkerIter {
@@ -22,17 +23,28 @@ pub trait Walker<T> {
     }
 }
 
+/// A walker which can also be advanced from the back.
+///
+/// Both ends meet in the middle, every item is yielded only once.
+pub trait DoubleEndedWalker<T, Ix: IndexType = DefaultIx>: Walker<T, Ix> {
+    /// Advance to the next item from the back
+    fn walk_next_back(&mut self, context: &Tree<T, Ix>) -> Option<Self::Item>;
+}
+
+/// A walker which keeps returning `None` once it has returned `None`.
+pub trait FusedWalker<T, Ix: IndexType = DefaultIx>: Walker<T, Ix> {}
+
 /// A walker and its context wrapped into an iterator.
 #[derive(Clone, Debug)]
-pub struct WalkerIter<'a, W, T: 'a> {
+pub struct WalkerIter<'a, W, T: 'a, Ix: 'a + IndexType = DefaultIx> {
     walker: W,
-    arena: &'a Tree<T>,
+    arena: &'a Tree<T, Ix>,
 }
 
-impl<'a, W, T> WalkerIter<'a, W, T>
-    where W: Walker<T>
+impl<'a, W, T, Ix: IndexType> WalkerIter<'a, W, T, Ix>
+    where W: Walker<T, Ix>
 {
-    pub fn arena(&self) -> &'a Tree<T> {
+    pub fn arena(&self) -> &'a Tree<T, Ix> {
         self.arena
     }
 
@@ -45,11 +57,91 @@ impl<'a, W, T> WalkerIter<'a, W, T>
     }
 }
 
-impl<'a, W, T> Iterator for WalkerIter<'a, W, T>
-    where W: Walker<T>,
+impl<'a, W, T, Ix: IndexType> WalkerIter<'a, W, T, Ix>
+    where W: Walker<T, Ix, Item = NodeId<Ix>>
+{
+    /// Yield the data of the nodes instead of their ids.
+    pub fn data(self) -> Data<'a, W, T, Ix> {
+        Data(self)
+    }
+
+    /// Yield the ids of the nodes together with their data.
+    pub fn with_data(self) -> WithData<'a, W, T, Ix> {
+        WithData(self)
+    }
+}
+
+impl<'a, W, T, Ix: IndexType> Iterator for WalkerIter<'a, W, T, Ix>
+    where W: Walker<T, Ix>,
 {
     type Item = W::Item;
     fn next(&mut self) -> Option<Self::Item> {
         self.walker.walk_next(self.arena)
     }
 }
+
+impl<'a, W, T, Ix: IndexType> DoubleEndedIterator for WalkerIter<'a, W, T, Ix>
+    where W: DoubleEndedWalker<T, Ix>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.walker.walk_next_back(self.arena)
+    }
+}
+
+impl<'a, W, T, Ix: IndexType> FusedIterator for WalkerIter<'a, W, T, Ix>
+    where W: FusedWalker<T, Ix>,
+{}
+
+/// An iterator over the data of the nodes yielded by a walker.
+#[derive(Clone, Debug)]
+pub struct Data<'a, W, T: 'a, Ix: 'a + IndexType = DefaultIx>(WalkerIter<'a, W, T, Ix>);
+
+impl<'a, W, T, Ix: IndexType> Iterator for Data<'a, W, T, Ix>
+    where W: Walker<T, Ix, Item = NodeId<Ix>>,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        let arena = self.0.arena;
+        self.0.next().map(|id| &arena[id].data)
+    }
+}
+
+impl<'a, W, T, Ix: IndexType> DoubleEndedIterator for Data<'a, W, T, Ix>
+    where W: DoubleEndedWalker<T, Ix, Item = NodeId<Ix>>,
+{
+    fn next_back(&mut self) -> Option<&'a T> {
+        let arena = self.0.arena;
+        self.0.next_back().map(|id| &arena[id].data)
+    }
+}
+
+impl<'a, W, T, Ix: IndexType> FusedIterator for Data<'a, W, T, Ix>
+    where W: FusedWalker<T, Ix, Item = NodeId<Ix>>,
+{}
+
+/// An iterator over the ids of the nodes yielded by a walker together with their data.
+#[derive(Clone, Debug)]
+pub struct WithData<'a, W, T: 'a, Ix: 'a + IndexType = DefaultIx>(WalkerIter<'a, W, T, Ix>);
+
+impl<'a, W, T, Ix: IndexType> Iterator for WithData<'a, W, T, Ix>
+    where W: Walker<T, Ix, Item = NodeId<Ix>>,
+{
+    type Item = (NodeId<Ix>, &'a T);
+    fn next(&mut self) -> Option<(NodeId<Ix>, &'a T)> {
+        let arena = self.0.arena;
+        self.0.next().map(|id| (id, &arena[id].data))
+    }
+}
+
+impl<'a, W, T, Ix: IndexType> DoubleEndedIterator for WithData<'a, W, T, Ix>
+    where W: DoubleEndedWalker<T, Ix, Item = NodeId<Ix>>,
+{
+    fn next_back(&mut self) -> Option<(NodeId<Ix>, &'a T)> {
+        let arena = self.0.arena;
+        self.0.next_back().map(|id| (id, &arena[id].data))
+    }
+}
+
+impl<'a, W, T, Ix: IndexType> FusedIterator for WithData<'a, W, T, Ix>
+    where W: FusedWalker<T, Ix, Item = NodeId<Ix>>,
+{}
@@ -2,10 +2,16 @@ extern crate indextree;
 #[cfg(feature = "par_iter")]
 extern crate rayon;
 
-use indextree::Tree;
+use indextree::{NodeEdge, NodeError, NodeId, Tree, Walker};
+use std::{cmp, fmt, mem};
 #[cfg(feature = "par_iter")]
 use rayon::prelude::*;
 
+/// Concatenate the data of the given nodes, to check the order of a walk at a glance.
+fn data<T: fmt::Display, I: IntoIterator<Item = NodeId>>(ids: I, arena: &Tree<T>) -> String {
+    ids.into_iter().map(|id| arena[id].data.to_string()).collect()
+}
+
 #[test]
 fn arenatree_success_create() {
     let mut new_counter = 0;
@@ -15,7 +21,7 @@ fn arenatree_success_create() {
             new_counter += 1;
             arena.new_node(new_counter)
         }};
-    };
+    }
 
     let a = new!(); // 1
     a.append(new!(), arena); // 2
@@ -33,18 +39,148 @@ fn arenatree_success_create() {
     arena[c].previous_sibling().unwrap().detach(arena);
 
     assert_eq!(
-        b.descendants(arena).map(|node| arena[node].data).collect::<Vec<_>>(),
+        b.descendants().iter(arena).map(|node| arena[node].data).collect::<Vec<_>>(),
         [5, 6, 7, 1, 4, 2, 3, 9, 10]
     );
 }
 
 #[test]
-#[should_panic]
+fn arenatree_success_prepend() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    a.prepend(b, arena);
+    a.prepend(c, arena);
+    assert_eq!(a.children(arena).iter(arena).collect::<Vec<_>>(), [c, b]);
+    assert_eq!(arena[a].last_child(), Some(b));
+}
+
+#[test]
+#[should_panic(expected = "the node would become its own descendant")]
 fn arenatree_failure_prepend() {
     let arena = &mut Tree::new();
     let a = arena.new_node(1);
     let b = arena.new_node(2);
     a.prepend(b, arena);
+    b.prepend(a, arena);
+}
+
+#[test]
+fn arenatree_failure_cycle() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    a.append(b, arena);
+    b.append(c, arena);
+    c.append(d, arena);
+
+    let before = arena.clone();
+    assert!(catch_unwind(AssertUnwindSafe(|| d.append(a, arena))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| c.prepend(b, arena))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| d.insert_after(b, arena))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| c.insert_before(a, arena))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| d.insert_before(c, arena))).is_err());
+    assert_eq!(*arena, before);
+
+    // Moving a descendant next to its ancestor is fine.
+    c.insert_after(d, arena);
+    assert_eq!(arena[d].parent(), Some(b));
+    assert_eq!(b.children(arena).iter(arena).collect::<Vec<_>>(), [c, d]);
+}
+
+#[test]
+fn arenatree_try_insert() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    assert_eq!(a.try_append(b, arena), Ok(()));
+    assert_eq!(b.try_prepend(c, arena), Ok(()));
+
+    let before = arena.clone();
+    assert_eq!(a.try_append(a, arena), Err(NodeError::SameNode));
+    assert_eq!(c.try_append(a, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(c.try_prepend(b, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(c.try_insert_after(b, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(b.try_insert_before(a, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(*arena, before);
+
+    assert_eq!(b.try_insert_after(c, arena), Ok(()));
+    assert_eq!(c.try_insert_before(b, arena), Ok(()));
+    assert_eq!(a.children(arena).iter(arena).collect::<Vec<_>>(), [b, c]);
+}
+
+#[test]
+fn arenatree_try_detach() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    a.append(b, arena);
+    assert_eq!(b.try_detach(arena), Ok(()));
+    assert_eq!(arena[b].parent(), None);
+
+    b.orphan(arena);
+    assert_eq!(b.try_detach(arena), Err(NodeError::Removed));
+    assert_eq!(a.try_append(b, arena), Err(NodeError::Removed));
+
+    let other = &mut Tree::new();
+    other.new_node(1);
+    other.new_node(2).orphan(other);
+    let foreign = other.new_node(3);
+    assert_eq!(foreign.try_detach(arena), Err(NodeError::NotInThisTree));
+
+    let c = arena.new_node(3);
+    c.orphan(arena);
+    arena.compact();
+    assert_eq!(a.try_append(c, arena), Err(NodeError::OutOfBounds));
+}
+
+#[test]
+fn arenatree_node_id() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    assert_eq!(arena.node_id(0), Some(a));
+    assert_eq!(arena.node_id(1), Some(b));
+    assert_eq!(arena.node_id(2), None);
+    a.orphan(arena);
+    assert_eq!(arena.node_id(0), None);
+    let c = arena.new_node(3);
+    assert_eq!(arena.node_id(0), Some(c));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn arenatree_foreign_id() {
+    let arena = &mut Tree::new();
+    let other = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = other.new_node(2);
+    let c = other.new_node(3);
+    assert!(arena.get(b).is_none());
+    assert!(arena.get_mut(b).is_none());
+    assert_eq!(a.try_append(c, arena), Err(NodeError::NotInThisTree));
+    assert_eq!(b.try_append(c, other), Ok(()));
+
+    // Clones are made of the same nodes.
+    let clone = arena.clone();
+    assert_eq!(clone[a].data, 1);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "the node id belongs to another tree")]
+fn arenatree_failure_index_foreign() {
+    let arena = &mut Tree::new();
+    let other = &mut Tree::new();
+    arena.new_node(1);
+    let a = other.new_node(1);
+    let _ = arena[a].data;
 }
 
 #[test]
@@ -53,9 +189,9 @@ fn arenatree_success_detach() {
     let a = arena.new_node(1);
     let b = arena.new_node(1);
     a.append(b, arena);
-    assert_eq!(b.ancestors(arena).into_iter().count(), 2);
+    assert_eq!(b.ancestors().iter(arena).count(), 2);
     b.detach(arena);
-    assert_eq!(b.ancestors(arena).into_iter().count(), 1);
+    assert_eq!(b.ancestors().iter(arena).count(), 1);
 }
 
 #[test]
@@ -72,6 +208,323 @@ fn arenatree_get_mut() {
     assert_eq!(arena.get_mut(id).unwrap().data, 1);
 }
 
+#[test]
+fn arenatree_stale_id_after_reuse() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    a.append(b, arena);
+    b.orphan(arena);
+    assert!(arena.get(b).is_none());
+    assert!(arena.get_mut(b).is_none());
+
+    let c = arena.new_node(3);
+    assert_ne!(b, c);
+    assert!(arena.get(b).is_none());
+    assert_eq!(arena[c].data, 3);
+}
+
+#[test]
+fn arenatree_stale_id_after_clear() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    arena.clear();
+    let b = arena.new_node(2);
+    assert!(arena.get(a).is_none());
+    assert_eq!(arena[b].data, 2);
+}
+
+#[test]
+#[should_panic(expected = "the node has been removed")]
+fn arenatree_failure_index_stale() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    a.orphan(arena);
+    arena.new_node(2);
+    let _ = arena[a].data;
+}
+
+#[test]
+fn arenatree_remove() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    let e = arena.new_node(5);
+    a.append(b, arena);
+    a.append(c, arena);
+    b.append(d, arena);
+    b.append(e, arena);
+
+    assert_eq!(arena.remove(b), Some(2));
+    assert_eq!(arena.remove(b), None);
+    assert!(arena.get(b).is_none());
+    assert_eq!(arena.count(), 4);
+    assert_eq!(
+        a.descendants().iter(arena).map(|node| arena[node].data).collect::<Vec<_>>(),
+        [1, 4, 5, 3]
+    );
+    assert_eq!(a.reverse_children(arena).iter(arena).collect::<Vec<_>>(), [c, e, d]);
+    assert_eq!(arena[d].parent(), Some(a));
+    assert_eq!(arena[e].parent(), Some(a));
+}
+
+#[test]
+fn arenatree_remove_subtree() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    a.append(b, arena);
+    a.append(c, arena);
+    b.append(d, arena);
+
+    assert_eq!(arena.remove_subtree(b), vec![(b, 2), (d, 4)]);
+    assert!(arena.remove_subtree(b).is_empty());
+    assert_eq!(arena.count(), 2);
+    assert_eq!(arena.iter().map(|(_, node)| node.data).collect::<Vec<_>>(), [1, 3]);
+    assert_eq!(a.children(arena).iter(arena).collect::<Vec<_>>(), [c]);
+
+    // Every freed slot is reused exactly once.
+    let e = arena.new_node(5);
+    let f = arena.new_node(6);
+    assert_ne!(e, f);
+    assert_eq!((arena[e].data, arena[f].data), (5, 6));
+    assert!(arena.get(b).is_none() && arena.get(d).is_none());
+    assert_eq!(arena.count(), 4);
+}
+
+#[test]
+fn arenatree_compact() {
+    let arena = &mut Tree::new();
+    let ids = (0..8).map(|i| arena.new_node(i)).collect::<Vec<_>>();
+    for &id in &ids[1..] {
+        ids[0].append(id, arena);
+    }
+    ids[2].append(ids[3], arena);
+    ids[1].orphan(arena);
+    ids[5].orphan(arena);
+    ids[6].orphan(arena);
+
+    let remap = arena.compact();
+    assert_eq!(remap.len(), 5);
+    assert_eq!(arena.len(), 5);
+    assert_eq!(remap[&ids[0]], ids[0]);
+    let new_ids = [0, 2, 3, 4, 7].iter().map(|&i| remap[&ids[i]]).collect::<Vec<_>>();
+    for (&id, data) in new_ids.iter().zip(&[0, 2, 3, 4, 7]) {
+        assert_eq!(arena[id].data, *data);
+    }
+    assert_eq!(
+        new_ids[0].descendants().iter(arena).map(|id| arena[id].data).collect::<Vec<_>>(),
+        [0, 2, 3, 4, 7]
+    );
+    assert_eq!(arena[new_ids[2]].parent(), Some(new_ids[1]));
+
+    // Old ids of moved nodes do not resolve anymore, not even after the arena grew back.
+    for &i in &[2, 3, 4, 7] {
+        assert!(arena.get(ids[i]).is_none());
+    }
+    let new = (0..3).map(|i| arena.new_node(10 + i)).collect::<Vec<_>>();
+    assert_eq!(arena.len(), 8);
+    for &i in &[1, 2, 3, 4, 5, 6, 7] {
+        assert!(arena.get(ids[i]).is_none());
+    }
+    assert_eq!(new.iter().map(|&id| arena[id].data).collect::<Vec<_>>(), [10, 11, 12]);
+}
+
+#[test]
+fn arenatree_relayout() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    let e = arena.new_node(5);
+    let f = arena.new_node(6);
+    let g = arena.new_node(7);
+    let h = arena.new_node(8);
+    d.append(b, arena);
+    d.prepend(e, arena);
+    b.append(a, arena);
+    b.append(g, arena);
+    h.append(c, arena);
+    f.orphan(arena);
+    assert!(arena.subtree_iter(d).is_none());
+
+    let remap = arena.relayout();
+    assert_eq!(remap.len(), 7);
+    assert_eq!(
+        arena.iter().map(|(_, node)| node.data).collect::<Vec<_>>(),
+        [4, 5, 2, 1, 7, 8, 3]
+    );
+    let (d, b, h) = (remap[&d], remap[&b], remap[&h]);
+    assert_eq!(
+        arena.subtree_iter(b).unwrap().map(|(id, _)| id).collect::<Vec<_>>(),
+        b.descendants().iter(arena).collect::<Vec<_>>()
+    );
+    assert_eq!(arena.subtree_iter(d).unwrap().count(), 5);
+    assert_eq!(arena.subtree_iter(h).unwrap().map(|(_, node)| node.data).collect::<Vec<_>>(), [8, 3]);
+
+    arena.new_node(9);
+    assert_eq!(arena.subtree_iter(d).unwrap().count(), 5);
+    h.append(d, arena);
+    assert!(arena.subtree_iter(d).is_none());
+}
+
+#[test]
+fn arenatree_roots() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    let e = arena.new_node(5);
+    assert_eq!(arena.roots().collect::<Vec<_>>(), [a, b, c, d, e]);
+
+    a.append(b, arena);
+    b.insert_after(c, arena);
+    d.insert_before(e, arena);
+    assert_eq!(arena.roots().collect::<Vec<_>>(), [a, d, e]);
+    c.detach(arena);
+    assert_eq!(arena.roots().collect::<Vec<_>>(), [a, c, d, e]);
+    arena.remove(a);
+    assert_eq!(arena.roots().collect::<Vec<_>>(), [b, c, d, e]);
+    d.orphan(arena);
+    assert_eq!(arena.roots().collect::<Vec<_>>(), [b, c, e]);
+
+    assert_eq!(arena.root(), Some(b));
+    assert_eq!(arena.set_root(e), Ok(()));
+    assert_eq!(arena.root(), Some(e));
+    c.append(e, arena);
+    assert_eq!(arena.root(), Some(b));
+    assert_eq!(arena.set_root(d), Err(NodeError::Removed));
+    e.detach(arena);
+    let remap = arena.compact();
+    assert_eq!(arena.root(), Some(remap[&e]));
+}
+
+#[test]
+fn arenatree_forest_traverse() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    c.append(b, arena);
+    assert_eq!(
+        arena.forest_traverse().iter(arena).collect::<Vec<_>>(),
+        [
+            NodeEdge::Start(a),
+            NodeEdge::End(a),
+            NodeEdge::Start(c),
+            NodeEdge::Start(b),
+            NodeEdge::End(b),
+            NodeEdge::End(c),
+            NodeEdge::Start(d),
+            NodeEdge::End(d),
+        ]
+    );
+    let empty = &Tree::<()>::new();
+    assert_eq!(empty.forest_traverse().iter(empty).count(), 0);
+}
+
+#[test]
+fn arenatree_append_subtree() {
+    let from = &mut Tree::new();
+    let a = from.new_node(1);
+    let b = from.new_node(2);
+    let c = from.new_node(3);
+    b.append(c, from);
+    a.orphan(from);
+
+    let into = &mut Tree::new();
+    let mut root = into.new_node(0);
+    let copy = root.append_subtree(from, into).unwrap();
+    assert_eq!(into[copy].data, 2);
+    assert_eq!(
+        root.descendants().iter(into).map(|id| into[id].data).collect::<Vec<_>>(),
+        [0, 2, 3]
+    );
+}
+
+#[test]
+fn arenatree_copy_subtree() {
+    let from = &mut Tree::new();
+    let a = from.new_node("a");
+    let b = from.new_node("b");
+    let c = from.new_node("c");
+    let d = from.new_node("d");
+    a.append(b, from);
+    b.append(c, from);
+    b.append(d, from);
+
+    let into = &mut Tree::new();
+    let x = into.new_node("x");
+    let new_b = into.copy_subtree(from, b, Some(x));
+    assert_eq!(into[new_b].parent(), Some(x));
+    assert_eq!(x.descendants().iter(into).data().cloned().collect::<String>(), "xbcd");
+
+    let (new_c, new_ids) = into.copy_subtree_with_ids(from, c, None);
+    assert_eq!(new_ids.len(), 1);
+    assert_eq!(new_ids[&c], new_c);
+    assert_eq!(into.roots().collect::<Vec<_>>(), [x, new_c]);
+
+    let (new_a, new_ids) = into.copy_subtree_with_ids(from, a, Some(new_c));
+    assert_eq!(new_ids.len(), 4);
+    for id in a.descendants().iter(from) {
+        assert_eq!(into[new_ids[&id]].data, from[id].data);
+    }
+    assert_eq!(into[new_ids[&d]].parent(), Some(new_ids[&b]));
+    assert_eq!(into[new_a].parent(), Some(new_c));
+
+    let extracted = from.extract_subtree(b);
+    assert_eq!(extracted.len(), 3);
+    let root = extracted.root().unwrap();
+    assert_eq!(root.descendants().iter(&extracted).data().cloned().collect::<String>(), "bcd");
+    assert_eq!(from.extract_subtree_into(d, into), into.roots().last().unwrap());
+    assert_eq!(into.len(), 10);
+}
+
+#[test]
+fn arenatree_deep_chain() {
+    // Appending leaves top-down does not walk up the growing chain.
+    let arena = &mut Tree::new();
+    let root = arena.new_node(0);
+    let mut leaf = root;
+    for i in 1..100_000 {
+        let node = arena.new_node(i);
+        leaf.append(node, arena);
+        leaf = node;
+    }
+    assert_eq!(leaf.ancestors().iter(arena).count(), 100_000);
+    assert_eq!(root.try_append(leaf, arena), Ok(()));
+    assert_eq!(leaf.try_prepend(root, arena), Err(NodeError::WouldCreateCycle));
+}
+
+#[test]
+fn arenatree_copy_deep_chain() {
+    let from = &mut Tree::new();
+    let root = from.new_node(0);
+    let mut leaf = root;
+    for i in 1..100_000 {
+        let node = from.new_node(i);
+        leaf.append(node, from);
+        leaf = node;
+    }
+
+    let copy = from.extract_subtree(root);
+    assert_eq!(copy.len(), 100_000);
+    let copy_root = copy.root().unwrap();
+    assert!(copy_root.descendants().iter(&copy).data().cloned().eq(0..100_000));
+
+    let into = &mut Tree::new();
+    let (_, new_ids) = into.copy_subtree_with_ids(from, root, None);
+    assert_eq!(into[new_ids[&leaf]].data, 99_999);
+    assert_eq!(new_ids[&leaf].ancestors().iter(into).count(), 100_000);
+}
+
 #[test]
 fn arenatree_iter() {
     let arena = &mut Tree::new();
@@ -84,7 +537,663 @@ fn arenatree_iter() {
     a.append(d, arena);
 
     let node_refs = arena.iter().collect::<Vec<_>>();
-    assert_eq!(node_refs, vec![&arena[a], &arena[b], &arena[c], &arena[d]]);
+    assert_eq!(node_refs, vec![(a, &arena[a]), (b, &arena[b]), (c, &arena[c]), (d, &arena[d])]);
+}
+
+#[test]
+fn arenatree_live_nodes() {
+    let arena = &mut Tree::new();
+    assert!(arena.is_empty());
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    a.append(b, arena);
+    assert_eq!(arena.len(), 3);
+
+    b.orphan(arena);
+    assert_eq!(arena.len(), 2);
+    assert!(arena.capacity() >= 3);
+    for (_, node) in arena.iter_mut() {
+        node.data *= 10;
+    }
+    assert_eq!(arena.iter().collect::<Vec<_>>(), [(a, &arena[a]), (c, &arena[c])]);
+    assert_eq!((arena[a].data, arena[c].data), (10, 30));
+
+    a.orphan(arena);
+    c.orphan(arena);
+    assert!(arena.is_empty());
+    assert_eq!(arena.iter().count(), 0);
+}
+
+#[test]
+fn arenatree_breadth_first() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    let e = arena.new_node("e");
+    let f = arena.new_node("f");
+    a.append(b, arena);
+    b.append(c, arena);
+    b.append(d, arena);
+    a.append(e, arena);
+    e.append(f, arena);
+
+    let levels = a.breadth_first().iter(arena).map(|(id, level)| (arena[id].data, level));
+    assert_eq!(levels.collect::<Vec<_>>(), [("a", 0), ("b", 1), ("e", 1), ("c", 2), ("d", 2), ("f", 2)]);
+    assert_eq!(e.breadth_first().iter(arena).collect::<Vec<_>>(), [(e, 0), (f, 1)]);
+
+    // The walker does not borrow the arena between steps.
+    let mut walker = b.breadth_first();
+    while let Some((id, _)) = walker.walk_next(arena) {
+        arena[id].data = "x";
+    }
+    assert_eq!(a.descendants().iter(arena).map(|id| arena[id].data).collect::<String>(), "axxxef");
+}
+
+#[test]
+fn arenatree_traverse_with_depth() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    a.append(b, arena);
+    b.append(c, arena);
+    a.append(d, arena);
+
+    let indented = a
+        .descendants_with_depth()
+        .iter(arena)
+        .map(|(id, depth)| format!("{}{}", "  ".repeat(depth), arena[id].data))
+        .collect::<Vec<_>>();
+    assert_eq!(indented, ["a", "  b", "    c", "  d"]);
+
+    assert_eq!(
+        b.traverse_with_depth().iter(arena).collect::<Vec<_>>(),
+        [(NodeEdge::Start(b), 0), (NodeEdge::Start(c), 1), (NodeEdge::End(c), 1), (NodeEdge::End(b), 0)]
+    );
+    let max_depth = a.descendants_with_depth().iter(arena).map(|(_, depth)| depth).max();
+    assert_eq!(max_depth, Some(2));
+}
+
+#[test]
+fn arenatree_traverse_with_depth_mutated() {
+    let arena = &mut Tree::new();
+    let r = arena.new_node("r");
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    r.append(a, arena);
+    a.append(b, arena);
+    let z1 = arena.new_node("z1");
+    let z2 = arena.new_node("z2");
+    z1.append(z2, arena);
+
+    // Moving `a` to another tree makes the walk climb through more ancestors than it went down.
+    let mut walk = r.traverse_with_depth();
+    let mut edges = Vec::new();
+    while let Some((edge, depth)) = walk.walk_next(arena) {
+        if edge == NodeEdge::Start(a) {
+            z2.append(a, arena);
+        }
+        edges.push((edge, depth));
+    }
+    assert_eq!(
+        edges,
+        [
+            (NodeEdge::Start(r), 0),
+            (NodeEdge::Start(a), 1),
+            (NodeEdge::Start(b), 2),
+            (NodeEdge::End(b), 2),
+            (NodeEdge::End(a), 1),
+            (NodeEdge::End(z2), 0),
+        ]
+    );
+    assert_eq!(walk.walk_next(arena), None);
+}
+
+#[test]
+fn arenatree_descendants_post_order() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    let e = arena.new_node("e");
+    a.append(b, arena);
+    b.append(c, arena);
+    b.append(d, arena);
+    a.append(e, arena);
+
+    assert_eq!(data(a.descendants_post_order().iter(arena), arena), "cdbea");
+    assert_eq!(data(a.reverse_descendants_post_order().iter(arena), arena), "aebdc");
+    assert_eq!(c.descendants_post_order().iter(arena).collect::<Vec<_>>(), [c]);
+
+    // Summing up subtree sizes bottom-up.
+    let mut sizes = vec![0; 5];
+    for id in a.descendants_post_order().iter(arena) {
+        let children = id.children(arena).iter(arena);
+        sizes[id.index()] = 1 + children.map(|child| sizes[child.index()]).sum::<usize>();
+    }
+    assert_eq!(sizes, [5, 3, 1, 1, 1]);
+}
+
+#[test]
+fn arenatree_traverse_pruning() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    let e = arena.new_node("e");
+    let f = arena.new_node("f");
+    a.append(b, arena);
+    b.append(c, arena);
+    a.append(d, arena);
+    d.append(e, arena);
+    a.append(f, arena);
+
+    let mut traverse = a.traverse().iter(arena);
+    let mut edges = vec![];
+    while let Some(edge) = traverse.next() {
+        edges.push(edge);
+        match edge {
+            NodeEdge::Start(id) if id == b => traverse.inner_mut().skip_subtree(),
+            NodeEdge::End(id) if id == e => traverse.inner_mut().stop(),
+            _ => {}
+        }
+    }
+    assert_eq!(
+        edges,
+        [
+            NodeEdge::Start(a),
+            NodeEdge::Start(b),
+            NodeEdge::End(b),
+            NodeEdge::Start(d),
+            NodeEdge::Start(e),
+            NodeEdge::End(e),
+        ]
+    );
+
+    let mut descendants = a.descendants();
+    let mut found = vec![];
+    while let Some(id) = descendants.walk_next(arena) {
+        found.push(arena[id].data);
+        if id == d {
+            descendants.skip_subtree();
+        }
+    }
+    assert_eq!(found, ["a", "b", "c", "d", "f"]);
+}
+
+#[test]
+fn arenatree_cursor() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    a.append(b, arena);
+    a.append(c, arena);
+    c.append(d, arena);
+
+    let mut cursor = arena.cursor(a);
+    assert!(!cursor.goto_parent());
+    assert!(cursor.goto_nth_child(1));
+    assert_eq!(cursor.node_id(), c);
+    assert!(!cursor.goto_next_sibling());
+    assert!(cursor.goto_previous_sibling());
+    assert_eq!(cursor.node().data, "b");
+    assert!(!cursor.goto_first_child());
+    assert!(!cursor.goto_nth_child(0));
+    assert!(cursor.goto_parent());
+    assert!(cursor.goto_last_child());
+    assert!(cursor.goto_first_child());
+    assert_eq!(cursor.node_id(), d);
+
+    let mut cursor = arena.cursor_mut(b);
+    cursor.node_mut().data = "B";
+    let e = cursor.insert_after("e");
+    cursor.insert_before("f");
+    assert_eq!(cursor.node_id(), b);
+    assert!(cursor.goto_next_sibling());
+    assert_eq!(cursor.node_id(), e);
+    assert!(cursor.goto_next_sibling());
+    assert!(cursor.goto_first_child());
+
+    // Removing moves to the replacing children, siblings or the parent in this order.
+    assert_eq!(cursor.remove(), Some("d"));
+    assert_eq!(cursor.node_id(), c);
+    assert!(cursor.goto_parent());
+    assert!(cursor.goto_last_child());
+    assert_eq!(cursor.remove(), Some("c"));
+    assert_eq!(cursor.node_id(), e);
+    assert!(cursor.goto_parent());
+    let mut cursor = cursor.as_cursor();
+    cursor.goto_first_child();
+    let children = a.children(cursor.tree()).iter(cursor.tree()).map(|id| cursor.tree()[id].data);
+    assert_eq!(children.collect::<Vec<_>>(), ["f", "B", "e"]);
+    assert_eq!(cursor.node().data, "f");
+
+    let lone = arena.new_node("lone");
+    assert_eq!(arena.cursor_mut(lone).remove(), None);
+    assert_eq!(arena[lone].data, "lone");
+}
+
+#[test]
+fn arenatree_double_ended() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    let e = arena.new_node("e");
+    a.append(b, arena);
+    b.append(c, arena);
+    a.append(d, arena);
+    a.append(e, arena);
+
+    let mut children = a.children(arena).iter(arena);
+    assert_eq!(children.next(), Some(b));
+    assert_eq!(children.next_back(), Some(e));
+    assert_eq!(children.next_back(), Some(d));
+    assert_eq!(children.next(), None);
+    assert_eq!(children.next_back(), None);
+    assert_eq!(a.reverse_children(arena).iter(arena).rev().collect::<Vec<_>>(), [b, d, e]);
+
+    let forward = a.traverse().iter(arena).collect::<Vec<_>>();
+    let mut backward = a.traverse().iter(arena).rev().collect::<Vec<_>>();
+    assert_eq!(a.reverse_traverse().iter(arena).collect::<Vec<_>>(), backward);
+    backward.reverse();
+    assert_eq!(forward, backward);
+
+    // Both ends meet in the middle for every split point.
+    for split in 0..=forward.len() {
+        let mut traverse = a.traverse().iter(arena);
+        let mut edges = traverse.by_ref().take(split).collect::<Vec<_>>();
+        let mut tail = traverse.rev().collect::<Vec<_>>();
+        tail.reverse();
+        edges.extend(tail);
+        assert_eq!(edges, forward);
+    }
+
+    assert_eq!(data(a.descendants().iter(arena).rev(), arena), "edcba");
+    assert_eq!(data(a.descendants_post_order().iter(arena).rev(), arena), "aedbc");
+    assert_eq!(data(a.reverse_descendants_post_order().iter(arena).rev(), arena), "cbdea");
+
+    let mut descendants = c.descendants().iter(arena).fuse();
+    assert_eq!(descendants.next(), Some(c));
+    assert_eq!(descendants.next_back(), None);
+}
+
+#[test]
+fn arenatree_children_mutated() {
+    let arena = &mut Tree::new();
+    let root = arena.new_node('r');
+    for data in "abcd".chars() {
+        let child = arena.new_node(data);
+        root.append(child, arena);
+    }
+    let ids = root.children(arena).iter(arena).collect::<Vec<_>>();
+    let (a, b, d) = (ids[0], ids[1], ids[3]);
+
+    // Both ends meet in the middle for every split point.
+    for split in 0..=ids.len() {
+        let mut children = root.children(arena).iter(arena);
+        let mut visited = children.by_ref().take(split).collect::<Vec<_>>();
+        let mut tail = children.rev().collect::<Vec<_>>();
+        tail.reverse();
+        visited.extend(tail);
+        assert_eq!(visited, ids);
+    }
+
+    // Walking from one end follows the siblings as they are when each node is reached.
+    let mut children = root.children(arena);
+    let mut visited = Vec::new();
+    while let Some(child) = children.walk_next(arena) {
+        if child == a {
+            b.insert_after(d, arena);
+            let e = arena.new_node('e');
+            root.append(e, arena);
+        }
+        visited.push(child);
+    }
+    assert_eq!(data(visited, arena), "abdce");
+
+    let mut reverse_children = root.reverse_children(arena);
+    let mut visited = Vec::new();
+    while let Some(child) = reverse_children.walk_next(arena) {
+        if child == b {
+            let f = arena.new_node('f');
+            root.prepend(f, arena);
+        }
+        visited.push(child);
+    }
+    assert_eq!(data(visited, arena), "ecdbaf");
+}
+
+#[test]
+fn arenatree_axes() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    let e = arena.new_node("e");
+    let f = arena.new_node("f");
+    let g = arena.new_node("g");
+    let h = arena.new_node("h");
+    a.append(b, arena);
+    b.append(c, arena);
+    b.append(d, arena);
+    a.append(e, arena);
+    e.append(f, arena);
+    e.append(g, arena);
+    a.append(h, arena);
+
+    assert_eq!(data(f.ancestors_or_self().iter(arena), arena), "fea");
+    assert_eq!(data(f.strict_ancestors(arena).iter(arena), arena), "ea");
+    assert_eq!(data(a.strict_ancestors(arena).iter(arena), arena), "");
+    assert_eq!(data(e.descendants_or_self().iter(arena), arena), "efg");
+    assert_eq!(data(e.strict_descendants(arena).iter(arena), arena), "fg");
+    assert_eq!(data(c.strict_descendants(arena).iter(arena), arena), "");
+    assert_eq!(data(h.preceding_siblings_or_self().iter(arena), arena), "heb");
+    assert_eq!(data(h.strict_preceding_siblings(arena).iter(arena), arena), "eb");
+    assert_eq!(data(b.following_siblings_or_self().iter(arena), arena), "beh");
+    assert_eq!(data(b.strict_following_siblings(arena).iter(arena), arena), "eh");
+
+    assert_eq!(data(e.siblings(arena).iter(arena), arena), "bh");
+    assert_eq!(data(h.siblings(arena).iter(arena), arena), "be");
+    assert_eq!(data(a.siblings(arena).iter(arena), arena), "");
+
+    assert_eq!(data(c.following(arena).iter(arena), arena), "defgh");
+    assert_eq!(data(b.following(arena).iter(arena), arena), "efgh");
+    assert_eq!(data(h.following(arena).iter(arena), arena), "");
+    assert_eq!(data(g.preceding(arena).iter(arena), arena), "fdcb");
+    assert_eq!(data(h.preceding(arena).iter(arena), arena), "gfedcb");
+    assert_eq!(data(c.preceding(arena).iter(arena), arena), "");
+
+    // The axes of a node partition the tree.
+    for node in a.descendants().iter(arena) {
+        let mut all = node.ancestors_or_self().iter(arena).collect::<Vec<_>>();
+        all.extend(node.strict_descendants(arena).iter(arena));
+        all.extend(node.following(arena).iter(arena));
+        all.extend(node.preceding(arena).iter(arena));
+        all.sort();
+        assert_eq!(all, [a, b, c, d, e, f, g, h]);
+    }
+}
+
+#[test]
+fn arenatree_data_iterators() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    let e = arena.new_node(5);
+    a.append(b, arena);
+    a.append(c, arena);
+    c.append(e, arena);
+    // Storage order differs from tree order.
+    c.prepend(d, arena);
+
+    assert_eq!(a.descendants().iter(arena).data().collect::<Vec<_>>(), [&1, &2, &3, &4, &5]);
+    assert_eq!(a.children(arena).iter(arena).data().rev().collect::<Vec<_>>(), [&3, &2]);
+    assert_eq!(c.children(arena).iter(arena).with_data().collect::<Vec<_>>(), [(d, &4), (e, &5)]);
+
+    for (offset, data) in a.descendants_mut(arena).enumerate() {
+        *data += 10 * offset;
+    }
+    assert_eq!(a.descendants().iter(arena).data().cloned().collect::<Vec<_>>(), [1, 12, 23, 34, 45]);
+
+    let mut children = c.children_mut(arena);
+    assert_eq!(children.len(), 2);
+    *children.next_back().unwrap() = 0;
+    *children.next().unwrap() = 100;
+    assert_eq!(c.children(arena).iter(arena).data().cloned().collect::<Vec<_>>(), [100, 0]);
+    assert_eq!(b.children_mut(arena).count(), 0);
+}
+
+#[test]
+fn arenatree_transplant() {
+    // The payload is deliberately not `Clone`.
+    #[derive(Debug, PartialEq)]
+    struct Payload(&'static str);
+
+    let source = &mut Tree::new();
+    let a = source.new_node(Payload("a"));
+    let b = source.new_node(Payload("b"));
+    let c = source.new_node(Payload("c"));
+    let d = source.new_node(Payload("d"));
+    let e = source.new_node(Payload("e"));
+    a.append(b, source);
+    b.append(c, source);
+    b.append(d, source);
+    a.append(e, source);
+
+    let target = &mut Tree::new();
+    let x = target.new_node(Payload("x"));
+    let y = target.new_node(Payload("y"));
+    x.append(y, target);
+
+    let (new_b, new_ids) = target.transplant(source, b, Some(x));
+    assert_eq!(new_ids.len(), 3);
+    assert_eq!(new_ids[&b], new_b);
+    assert_eq!(target[new_b].data, Payload("b"));
+    assert_eq!(target[new_ids[&d]].data, Payload("d"));
+    assert_eq!(x.children(target).iter(target).collect::<Vec<_>>(), [y, new_b]);
+    assert_eq!(new_b.children(target).iter(target).collect::<Vec<_>>(), [new_ids[&c], new_ids[&d]]);
+
+    assert_eq!(source.len(), 2);
+    assert_eq!(source.get(c), None);
+    assert_eq!(a.children(source).iter(source).collect::<Vec<_>>(), [e]);
+
+    let (new_a, _) = target.transplant(source, a, None);
+    assert!(source.is_empty());
+    assert_eq!(target.roots().collect::<Vec<_>>(), [x, new_a]);
+    assert_eq!(target[target[new_a].first_child().unwrap()].data, Payload("e"));
+}
+
+#[test]
+fn arenatree_swap_and_replace() {
+    let arena = &mut Tree::new();
+    let a = arena.new_node("a");
+    let b = arena.new_node("b");
+    let c = arena.new_node("c");
+    let d = arena.new_node("d");
+    let e = arena.new_node("e");
+    let f = arena.new_node("f");
+    a.append(b, arena);
+    a.append(c, arena);
+    a.append(d, arena);
+    c.append(e, arena);
+    d.append(f, arena);
+    let preorder = |arena: &Tree<&str>| a.descendants().iter(arena).data().cloned().collect::<String>();
+
+    // Adjacent siblings, in both orders.
+    b.swap_with(c, arena);
+    assert_eq!(preorder(arena), "acebdf");
+    b.swap_with(c, arena);
+    assert_eq!(preorder(arena), "abcedf");
+
+    // Distant nodes at different depths.
+    b.swap_with(f, arena);
+    assert_eq!(preorder(arena), "afcedb");
+    assert_eq!(arena[b].parent(), Some(d));
+    f.swap_with(b, arena);
+    assert_eq!(preorder(arena), "abcedf");
+
+    assert_eq!(a.try_swap_with(e, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(e.try_swap_with(a, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(b.try_swap_with(b, arena), Err(NodeError::SameNode));
+
+    // Replacing with a sibling, a descendant and a detached node.
+    c.replace_with(d, arena);
+    assert_eq!(preorder(arena), "abdf");
+    assert_eq!(c.descendants().iter(arena).data().cloned().collect::<String>(), "ce");
+    d.replace_with(f, arena);
+    assert_eq!(preorder(arena), "abf");
+    assert!(arena.roots().any(|root| root == d));
+    b.replace_with(c, arena);
+    assert_eq!(preorder(arena), "acef");
+    assert_eq!(c.try_replace_with(a, arena), Err(NodeError::WouldCreateCycle));
+
+    // A root can be swapped with a node of another tree.
+    a.swap_with(b, arena);
+    assert_eq!(arena[a].parent(), None);
+    assert_eq!(arena[b].parent(), None);
+    assert_eq!(arena[d].first_child(), None);
+}
+
+#[test]
+fn arenatree_sort_children() {
+    let arena = &mut Tree::new();
+    let root = arena.new_node("r0");
+    for &data in &["a3", "b1", "c3", "d2"] {
+        let child = arena.new_node(data);
+        root.append(child, arena);
+    }
+    // Each node is named by a letter followed by its sort key.
+    let key = |data: &&str| data.as_bytes()[1];
+
+    root.stable_sort_children_by_key(arena, key);
+    assert_eq!(data(root.children(arena).iter(arena), arena), "b1d2a3c3");
+    assert_eq!(data(root.reverse_children(arena).iter(arena), arena), "c3a3d2b1");
+    root.stable_sort_children_by(arena, |a, b| key(b).cmp(&key(a)));
+    assert_eq!(data(root.children(arena).iter(arena), arena), "a3c3d2b1");
+    root.sort_children_by(arena, |a, b| a.cmp(b));
+    assert_eq!(data(root.children(arena).iter(arena), arena), "a3b1c3d2");
+    root.sort_children_by_key(arena, |name| cmp::Reverse(*name));
+    assert_eq!(data(root.children(arena).iter(arena), arena), "d2c3b1a3");
+    assert_eq!(data(root.reverse_children(arena).iter(arena), arena), "a3b1c3d2");
+
+    // Sort every level of the subtree, leaving the rest of the tree alone.
+    let c = root.children(arena).iter(arena).nth(1).unwrap();
+    let e = arena.new_node("e5");
+    let f = arena.new_node("f4");
+    let g = arena.new_node("g6");
+    c.append(e, arena);
+    c.append(f, arena);
+    f.append(g, arena);
+    let h = arena.new_node("h1");
+    f.append(h, arena);
+    root.sort_descendants_by(arena, |a, b| (key(a), a).cmp(&(key(b), b)));
+    assert_eq!(data(root.descendants().iter(arena), arena), "r0b1d2a3c3f4h1g6e5");
+    assert_eq!(arena[h].parent(), Some(f));
+    assert_eq!(arena[g].previous_sibling(), Some(h));
+}
+
+#[test]
+fn arenatree_splice() {
+    let arena = &mut Tree::new();
+    let root = arena.new_node('r');
+    let p = arena.new_node('p');
+    let nodes = arena.new_nodes("abcde".chars()).collect::<Vec<_>>();
+    let (a, b, c, d) = (nodes[0], nodes[1], nodes[2], nodes[3]);
+    for &node in &nodes {
+        root.append(node, arena);
+    }
+
+    p.append_range(b, d, arena);
+    assert_eq!(data(root.children(arena).iter(arena), arena), "ae");
+    assert_eq!(data(root.reverse_children(arena).iter(arena), arena), "ea");
+    assert_eq!(data(p.children(arena).iter(arena), arena), "bcd");
+    assert_eq!(data(p.reverse_children(arena).iter(arena), arena), "dcb");
+    assert_eq!(arena[c].parent(), Some(p));
+
+    a.insert_range_after(c, d, arena);
+    assert_eq!(data(root.children(arena).iter(arena), arena), "acde");
+    assert_eq!(data(root.reverse_children(arena).iter(arena), arena), "edca");
+    assert_eq!(data(p.children(arena).iter(arena), arena), "b");
+    assert_eq!(arena[d].parent(), Some(root));
+
+    let f = arena.new_node('f');
+    c.append(f, arena);
+    assert_eq!(c.try_append_range(c, d, arena), Err(NodeError::SameNode));
+    assert_eq!(f.try_insert_range_after(a, c, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(p.try_append_range(d, c, arena), Err(NodeError::NotSiblings));
+    assert_eq!(f.try_move_children_to(f, arena), Ok(()));
+    assert_eq!(c.try_move_children_to(f, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(root.try_move_children_to(f, arena), Err(NodeError::WouldCreateCycle));
+    assert_eq!(data(root.children(arena).iter(arena), arena), "acde");
+
+    root.move_children_to(p, arena);
+    assert_eq!(data(root.children(arena).iter(arena), arena), "");
+    assert_eq!(data(p.children(arena).iter(arena), arena), "bacde");
+    assert_eq!(data(p.reverse_children(arena).iter(arena), arena), "edcab");
+    assert_eq!(arena[f].parent(), Some(c));
+    assert_eq!(arena.roots().count(), 2);
+
+    // Moving nodes next to a root turns them into roots.
+    p.insert_range_after(a, c, arena);
+    assert_eq!(data(p.children(arena).iter(arena), arena), "bde");
+    assert_eq!(data(p.following_siblings().iter(arena), arena), "pac");
+    assert_eq!(arena[a].parent(), None);
+    assert_eq!(arena.roots().count(), 4);
+    root.append_range(a, c, arena);
+    assert_eq!(data(root.children(arena).iter(arena), arena), "ac");
+    assert_eq!(arena[p].next_sibling(), None);
+    assert_eq!(arena.roots().count(), 2);
+}
+
+#[test]
+fn arenatree_capacity() {
+    let arena = &mut Tree::with_capacity(4);
+    assert!(arena.capacity() >= 4);
+    let a = arena.new_node(0);
+    let ids = arena.new_nodes(1..4);
+    assert_eq!(ids.len(), 3);
+    let ids = ids.collect::<Vec<_>>();
+    assert_eq!(ids.iter().map(|id| id.index()).collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(ids.iter().map(|&id| arena[id].data).collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(arena.roots().count(), 4);
+
+    // Bulk allocation does not fill the slots of removed nodes.
+    ids[0].orphan(arena);
+    let more = arena.new_nodes(vec![4, 5]).collect::<Vec<_>>();
+    assert_eq!(more.iter().map(|id| id.index()).collect::<Vec<_>>(), [4, 5]);
+    for &id in ids[1..].iter().chain(&more) {
+        a.append(id, arena);
+    }
+    assert_eq!(a.children(arena).iter(arena).map(|id| arena[id].data).collect::<Vec<_>>(), [2, 3, 4, 5]);
+
+    // Reserved room is enough for bulk allocation, even with a removed node around.
+    arena.reserve(100);
+    let capacity = arena.capacity();
+    assert!(capacity >= arena.len() + 100);
+    assert_eq!(arena.new_nodes(0..100).len(), 100);
+    assert_eq!(arena.capacity(), capacity);
+    arena.shrink_to_fit();
+    assert!(arena.capacity() >= 6);
+    assert_eq!(arena.new_node(6).index(), 1);
+}
+
+#[test]
+fn arenatree_index_type() {
+    assert_eq!(mem::size_of::<Option<NodeId>>(), mem::size_of::<NodeId>());
+    assert_eq!(mem::size_of::<Option<NodeId<u16>>>(), mem::size_of::<NodeId<u16>>());
+
+    let arena = &mut Tree::<_, u16>::default();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    a.append(b, arena);
+    assert_eq!((a.index(), b.index()), (0, 1));
+    assert_eq!(b.ancestors().iter(arena).collect::<Vec<_>>(), [b, a]);
+
+    b.orphan(arena);
+    let c = arena.new_node(3);
+    assert_eq!(c.index(), 1);
+    assert_eq!(arena.get(b), None);
+    assert_eq!(arena[c].data, 3);
+}
+
+#[cfg(not(debug_assertions))]
+#[test]
+fn arenatree_index_size() {
+    assert_eq!(mem::size_of::<Option<NodeId>>(), 8);
+    assert_eq!(mem::size_of::<Option<NodeId<u16>>>(), 4);
 }
 
 #[cfg(feature = "par_iter")]
@@ -99,6 +1208,7 @@ fn arenatree_par_iter() {
     b.append(c, arena);
     a.append(d, arena);
 
+    b.orphan(arena);
     let node_refs = arena.par_iter().collect::<Vec<_>>();
-    assert_eq!(node_refs, vec![&arena[a], &arena[b], &arena[c], &arena[d]]);
+    assert_eq!(node_refs, vec![(a, &arena[a]), (d, &arena[d])]);
 }